@@ -8,32 +8,48 @@ use alloc::string::{String, ToString as _};
 
 use hl_guest::asyncio::io::stdin;
 use hl_guest::asyncio::spawn;
-use hl_guest::asyncio::time::{sleep, Timeout as _};
+use hl_guest::asyncio::sync::CancellationToken;
+use hl_guest::asyncio::time::{interval, Timeout as _};
 use hl_guest::io::{stdout, Write as _};
-use hl_guest::{guest_function, print, println};
+use hl_guest::tty::IsTerminal as _;
+use hl_guest::{debug, guest_function, print, println};
+use tracing::{info_span, Instrument as _};
 
 #[guest_function("Main")]
 async fn main(name: String) -> i32 {
     println!("Hello {name}, you have 5s to enter your to be greeted");
 
-    spawn(async {
+    let countdown = async {
+        let mut ticks = interval(Duration::from_secs(1));
+        ticks.tick().await;
         println!("5 ...");
         for i in 1..5 {
-            sleep(Duration::from_secs(1)).await;
-            print!("\x1b[s\x1b[A\x1b[G{} ...\x1b[u", 5 - i);
-            let _ = stdout().flush();
+            ticks.tick().await;
+            if stdout().is_terminal() {
+                print!("\x1b[s\x1b[A\x1b[G{} ...\x1b[u", 5 - i);
+                let _ = stdout().flush();
+            } else {
+                println!("{} ...", 5 - i);
+            }
         }
-    });
+    };
+    spawn(countdown.instrument(info_span!("countdown")));
 
-    let name = stdin()
+    let stdin = stdin();
+    let read_name = stdin
         .read_line_to_string()
         .timeout(Duration::from_secs(5))
-        .await
-        .transpose()
-        .unwrap()
-        .unwrap_or("anonymous".to_string());
+        .instrument(info_span!("read_name"));
+    // the host cancels the token on Ctrl-C
+    let token = CancellationToken::host();
+    let Some(name) = token.run_until_cancelled(read_name).await else {
+        println!("Cancelled, goodbye!");
+        return 130;
+    };
+    let name = name.transpose().unwrap().unwrap_or("anonymous".to_string());
 
     let name = name.trim();
+    debug!("read name {name:?} from stdin");
 
     println!("Hello {name:?}!");
 
@@ -0,0 +1,87 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+use core::future::Future;
+
+use alloc::string::String;
+
+use hl_guest::asyncio::channel::mpsc;
+use hl_guest::asyncio::notify::Notify;
+use hl_guest::asyncio::{spawn, yield_now, JoinSet};
+use hl_guest::time::Instant;
+use hl_guest::{guest_function, println};
+
+const ITERATIONS: u32 = 10_000;
+
+// Runs `iterations`, which loops `ITERATIONS` times, and prints the average
+// time of an iteration. The guest can't count its allocations, as the
+// allocator comes from `hyperlight-guest`: the `alloc` test of
+// `hl-guest-async` counts them for the same loops.. The guest can't count its allocations, as the
+// allocator comes from `hyperlight-guest`: the `alloc` test of
+// `hl-guest-async` counts them for the same loops.
+async fn bench(name: &str, iterations: impl Future<Output = ()>) {
+    let start = Instant::now();
+    iterations.await;
+    let elapsed = start.elapsed();
+    println!("{name:<20} {:>10?}/iter", elapsed / ITERATIONS);
+}
+
+#[guest_function("Main")]
+async fn main(_name: String) -> i32 {
+    println!("{ITERATIONS} iterations each");
+
+    bench("spawn + await", async {
+        for i in 0..ITERATIONS {
+            assert_eq!(spawn(async move { i }).await, Ok(i));
+        }
+    })
+    .await;
+
+    bench("JoinSet of 16 tasks", async {
+        for i in 0..ITERATIONS {
+            let mut set = JoinSet::new();
+            for j in 0..16 {
+                set.spawn(async move { i + j });
+            }
+            while set.join_next().await.is_some() {}
+        }
+    })
+    .await;
+
+    bench("yield_now", async {
+        for _ in 0..ITERATIONS {
+            yield_now().await;
+        }
+    })
+    .await;
+
+    bench("mpsc ping-pong", async {
+        let (ping, mut pings) = mpsc::channel(1);
+        let (pong, mut pongs) = mpsc::channel(1);
+        let echo = spawn(async move {
+            while let Ok(i) = pings.recv().await {
+                pong.send(i).await.unwrap();
+            }
+        });
+        for i in 0..ITERATIONS {
+            ping.send(i).await.unwrap();
+            assert_eq!(pongs.recv().await, Ok(i));
+        }
+        drop(ping);
+        echo.await.unwrap();
+    })
+    .await;
+
+    bench("Notify", async {
+        let notify = Notify::new();
+        for _ in 0..ITERATIONS {
+            let notified = notify.notified();
+            notify.notify_one();
+            notified.await;
+        }
+    })
+    .await;
+
+    0
+}
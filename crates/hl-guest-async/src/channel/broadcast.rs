@@ -0,0 +1,263 @@
+//! Multi-producer, multi-consumer channel where every receiver gets every
+//! value.
+//!
+//! The channel keeps the last `capacity` values. A receiver that falls
+//! further behind misses the oldest values, and is told how many with
+//! [`RecvError::Lagged`].
+
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+use core::fmt;
+use core::future::poll_fn;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use futures::{Sink, Stream};
+use spin::Mutex;
+
+use super::SendError;
+use crate::notify::{Notify, Waiter};
+
+/// Error returned when receiving from a [`channel`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecvError {
+    /// All the senders were dropped, and the receiver got all the values.
+    Closed,
+    /// The receiver fell behind and missed this many values. The next
+    /// receive returns the oldest value still in the channel.
+    Lagged(u64),
+}
+
+/// Error returned when trying to receive from a [`channel`] without waiting.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TryRecvError {
+    /// The receiver got all the values, but more could still be sent.
+    Empty,
+    /// All the senders were dropped, and the receiver got all the values.
+    Closed,
+    /// The receiver fell behind and missed this many values.
+    Lagged(u64),
+}
+
+impl fmt::Display for RecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecvError::Closed => f.write_str("channel closed"),
+            RecvError::Lagged(n) => write!(f, "channel lagged by {n}"),
+        }
+    }
+}
+
+impl fmt::Display for TryRecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TryRecvError::Empty => f.write_str("channel empty"),
+            TryRecvError::Closed => f.write_str("channel closed"),
+            TryRecvError::Lagged(n) => write!(f, "channel lagged by {n}"),
+        }
+    }
+}
+
+impl core::error::Error for RecvError {}
+impl core::error::Error for TryRecvError {}
+
+struct Shared<T> {
+    state: Mutex<State<T>>,
+    // notified when a value is sent, or the senders are dropped
+    notify: Notify,
+}
+
+struct State<T> {
+    buffer: VecDeque<T>,
+    capacity: usize,
+    // position of the first value in `buffer`, counting every value sent
+    head: u64,
+    senders: usize,
+    receivers: usize,
+}
+
+impl<T> State<T> {
+    // position of the next value to be sent
+    fn tail(&self) -> u64 {
+        self.head + self.buffer.len() as u64
+    }
+}
+
+/// Creates a channel that keeps the last `capacity` values.
+///
+/// # Panics
+///
+/// Panics if `capacity` is zero.
+pub fn channel<T: Clone>(capacity: usize) -> (Sender<T>, Receiver<T>) {
+    assert!(capacity > 0, "broadcast channel capacity must be non-zero");
+    let shared = Arc::new(Shared {
+        state: Mutex::new(State {
+            buffer: VecDeque::with_capacity(capacity),
+            capacity,
+            head: 0,
+            senders: 1,
+            receivers: 1,
+        }),
+        notify: Notify::new(),
+    });
+    let receiver = Receiver {
+        shared: shared.clone(),
+        next: 0,
+        waiter: Waiter::default(),
+    };
+    (Sender(shared), receiver)
+}
+
+/// Sends values to a broadcast [`channel`].
+pub struct Sender<T>(Arc<Shared<T>>);
+
+impl<T> Sender<T> {
+    /// Sends a value to all the receivers, and returns how many there are.
+    ///
+    /// Fails if there are no receivers.
+    pub fn send(&self, value: T) -> Result<usize, SendError<T>> {
+        let mut state = self.0.state.lock();
+        if state.receivers == 0 {
+            return Err(SendError(value));
+        }
+        if state.buffer.len() == state.capacity {
+            state.buffer.pop_front();
+            state.head += 1;
+        }
+        state.buffer.push_back(value);
+        let receivers = state.receivers;
+        drop(state);
+        self.0.notify.notify_waiters();
+        Ok(receivers)
+    }
+
+    /// Creates a receiver that gets the values sent from now on.
+    pub fn subscribe(&self) -> Receiver<T> {
+        let mut state = self.0.state.lock();
+        state.receivers += 1;
+        Receiver {
+            shared: self.0.clone(),
+            next: state.tail(),
+            waiter: Waiter::default(),
+        }
+    }
+
+    pub fn receiver_count(&self) -> usize {
+        self.0.state.lock().receivers
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.0.state.lock().senders += 1;
+        Sender(self.0.clone())
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut state = self.0.state.lock();
+        state.senders -= 1;
+        if state.senders == 0 {
+            drop(state);
+            self.0.notify.notify_waiters();
+        }
+    }
+}
+
+impl<T> Sink<T> for Sender<T> {
+    type Error = SendError<T>;
+
+    fn poll_ready(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: T) -> Result<(), Self::Error> {
+        self.send(item).map(|_| ())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Receives values from a broadcast [`channel`].
+///
+/// As a [`Stream`], it yields `Err(RecvError::Lagged(_))` when it falls
+/// behind, and ends when the channel is closed.
+pub struct Receiver<T> {
+    shared: Arc<Shared<T>>,
+    // position of the next value to receive
+    next: u64,
+    waiter: Waiter,
+}
+
+impl<T: Clone> Receiver<T> {
+    pub async fn recv(&mut self) -> Result<T, RecvError> {
+        poll_fn(|cx| self.poll_recv(cx)).await
+    }
+
+    pub fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Result<T, RecvError>> {
+        // the waiter is taken out so that `try_recv` can borrow `self`
+        let shared = self.shared.clone();
+        let mut waiter = core::mem::take(&mut self.waiter);
+        let poll = waiter.poll_until(&shared.notify, cx, || match self.try_recv() {
+            Ok(value) => Some(Ok(value)),
+            Err(TryRecvError::Closed) => Some(Err(RecvError::Closed)),
+            Err(TryRecvError::Lagged(n)) => Some(Err(RecvError::Lagged(n))),
+            Err(TryRecvError::Empty) => None,
+        });
+        self.waiter = waiter;
+        poll
+    }
+
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        let state = self.shared.state.lock();
+        if self.next < state.head {
+            let missed = state.head - self.next;
+            self.next = state.head;
+            return Err(TryRecvError::Lagged(missed));
+        }
+        match state.buffer.get((self.next - state.head) as usize) {
+            Some(value) => {
+                self.next += 1;
+                Ok(value.clone())
+            }
+            None if state.senders == 0 => Err(TryRecvError::Closed),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+}
+
+impl<T> Receiver<T> {
+    /// Creates a receiver that gets the values sent from now on.
+    pub fn resubscribe(&self) -> Receiver<T> {
+        let mut state = self.shared.state.lock();
+        state.receivers += 1;
+        Receiver {
+            shared: self.shared.clone(),
+            next: state.tail(),
+            waiter: Waiter::default(),
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.shared.state.lock().receivers -= 1;
+    }
+}
+
+impl<T: Clone> Stream for Receiver<T> {
+    type Item = Result<T, RecvError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match self.poll_recv(cx) {
+            Poll::Ready(Err(RecvError::Closed)) => Poll::Ready(None),
+            poll => poll.map(Some),
+        }
+    }
+}
@@ -0,0 +1,100 @@
+//! Channels to communicate between tasks.
+//!
+//! * [`oneshot`]: a single value from one sender to one receiver.
+//! * [`mpsc`]: many values from many senders to one receiver, bounded or
+//!   unbounded.
+//! * [`broadcast`]: many values from many senders to many receivers, each
+//!   receiver gets every value.
+//! * [`watch`]: a single value that many receivers can watch for changes.
+//!
+//! All of them detect when the other side is gone, e.g., receiving from a
+//! channel whose senders were all dropped fails with [`RecvError::Closed`].
+
+use core::fmt;
+
+pub mod broadcast;
+pub mod mpsc;
+pub mod oneshot;
+pub mod watch;
+
+/// The unbounded [`mpsc`] channel, under the names it had before the other
+/// channels were added.
+///
+/// Unlike the old channel, receiving fails with [`RecvError::Closed`] once
+/// all the senders were dropped.
+pub use mpsc::{
+    unbounded_channel as channel, UnboundedReceiver as Receiver, UnboundedSender as Sender,
+};
+
+/// Error returned when receiving from a channel whose senders were dropped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecvError {
+    Closed,
+}
+
+/// Error returned when trying to receive from a channel without waiting.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TryRecvError {
+    /// The channel is empty, but a value could still be sent.
+    Empty,
+    /// The channel is empty, and its senders were dropped.
+    Closed,
+}
+
+/// Error returned when sending to a channel whose receivers were dropped,
+/// with the value that couldn't be sent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SendError<T>(pub T);
+
+/// Error returned when trying to send to a channel without waiting, with the
+/// value that couldn't be sent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TrySendError<T> {
+    /// The channel is full.
+    Full(T),
+    /// The receivers were dropped.
+    Closed(T),
+}
+
+impl<T> TrySendError<T> {
+    pub fn into_inner(self) -> T {
+        match self {
+            TrySendError::Full(value) | TrySendError::Closed(value) => value,
+        }
+    }
+}
+
+impl fmt::Display for RecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("channel closed")
+    }
+}
+
+impl fmt::Display for TryRecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TryRecvError::Empty => f.write_str("channel empty"),
+            TryRecvError::Closed => f.write_str("channel closed"),
+        }
+    }
+}
+
+impl<T> fmt::Display for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("channel closed")
+    }
+}
+
+impl<T> fmt::Display for TrySendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrySendError::Full(_) => f.write_str("channel full"),
+            TrySendError::Closed(_) => f.write_str("channel closed"),
+        }
+    }
+}
+
+impl core::error::Error for RecvError {}
+impl core::error::Error for TryRecvError {}
+impl<T: fmt::Debug> core::error::Error for SendError<T> {}
+impl<T: fmt::Debug> core::error::Error for TrySendError<T> {}
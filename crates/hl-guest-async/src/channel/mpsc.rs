@@ -0,0 +1,401 @@
+//! Multi-producer, single-consumer channels.
+//!
+//! A bounded [`channel`] holds at most `capacity` values, and [`Sender::send`]
+//! waits until there's room for more. An [`unbounded_channel`] never waits.
+
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+use core::future::poll_fn;
+use core::pin::Pin;
+use core::task::{ready, Context, Poll};
+use futures::{Sink, Stream};
+use spin::Mutex;
+
+use super::{RecvError, SendError, TryRecvError, TrySendError};
+use crate::notify::{Notify, Waiter};
+
+struct Chan<T> {
+    state: Mutex<State<T>>,
+    // notified when a value is sent, or the senders are dropped
+    recv_notify: Notify,
+    // notified when a value is received, or the receiver is closed
+    send_notify: Notify,
+}
+
+struct State<T> {
+    queue: VecDeque<T>,
+    capacity: Option<usize>,
+    // room held by the senders that are ready as a `Sink`, for their next value
+    reserved: usize,
+    senders: usize,
+    receiver_closed: bool,
+}
+
+impl<T> State<T> {
+    fn has_room(&self) -> bool {
+        self.capacity
+            .is_none_or(|cap| self.queue.len() + self.reserved < cap)
+    }
+}
+
+impl<T> Chan<T> {
+    fn new(capacity: Option<usize>) -> Arc<Self> {
+        Arc::new(Chan {
+            state: Mutex::new(State {
+                queue: VecDeque::new(),
+                capacity,
+                reserved: 0,
+                senders: 1,
+                receiver_closed: false,
+            }),
+            recv_notify: Notify::new(),
+            send_notify: Notify::new(),
+        })
+    }
+
+    // Sends a value, in the room reserved with `reserve` if `reserved` is set
+    fn try_send(&self, value: T, reserved: bool) -> Result<(), TrySendError<T>> {
+        let mut state = self.state.lock();
+        if reserved {
+            state.reserved -= 1;
+        }
+        if state.receiver_closed {
+            return Err(TrySendError::Closed(value));
+        }
+        if !reserved && !state.has_room() {
+            return Err(TrySendError::Full(value));
+        }
+        state.queue.push_back(value);
+        drop(state);
+        self.recv_notify.notify_waiters();
+        Ok(())
+    }
+
+    // Reserves room for a value, and returns whether it did, or `None` if
+    // the channel is full. Nothing is reserved once the receiver is closed,
+    // as sending fails right away then.
+    fn reserve(&self) -> Option<bool> {
+        let mut state = self.state.lock();
+        if state.receiver_closed {
+            return Some(false);
+        }
+        if !state.has_room() {
+            return None;
+        }
+        state.reserved += 1;
+        Some(true)
+    }
+
+    fn release(&self) {
+        self.state.lock().reserved -= 1;
+        self.send_notify.notify_waiters();
+    }
+
+    fn try_recv(&self) -> Result<T, TryRecvError> {
+        let mut state = self.state.lock();
+        match state.queue.pop_front() {
+            Some(value) => {
+                let bounded = state.capacity.is_some();
+                drop(state);
+                if bounded {
+                    self.send_notify.notify_waiters();
+                }
+                Ok(value)
+            }
+            None if state.senders == 0 || state.receiver_closed => Err(TryRecvError::Closed),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    fn poll_recv(&self, waiter: &mut Waiter, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        waiter.poll_until(&self.recv_notify, cx, || match self.try_recv() {
+            Ok(value) => Some(Some(value)),
+            Err(TryRecvError::Closed) => Some(None),
+            Err(TryRecvError::Empty) => None,
+        })
+    }
+
+    fn is_closed(&self) -> bool {
+        self.state.lock().receiver_closed
+    }
+
+    fn close(&self) {
+        self.state.lock().receiver_closed = true;
+        self.send_notify.notify_waiters();
+    }
+
+    fn clone_sender(&self) {
+        self.state.lock().senders += 1;
+    }
+
+    fn drop_sender(&self) {
+        let mut state = self.state.lock();
+        state.senders -= 1;
+        if state.senders == 0 {
+            drop(state);
+            self.recv_notify.notify_waiters();
+        }
+    }
+}
+
+/// Creates a channel that holds at most `capacity` values.
+///
+/// # Panics
+///
+/// Panics if `capacity` is zero.
+pub fn channel<T>(capacity: usize) -> (Sender<T>, Receiver<T>) {
+    assert!(capacity > 0, "mpsc channel capacity must be non-zero");
+    let chan = Chan::new(Some(capacity));
+    let sender = Sender {
+        chan: chan.clone(),
+        waiter: Waiter::default(),
+        reserved: false,
+    };
+    let receiver = Receiver {
+        chan,
+        waiter: Waiter::default(),
+    };
+    (sender, receiver)
+}
+
+/// Creates a channel without a limit on the values it holds.
+pub fn unbounded_channel<T>() -> (UnboundedSender<T>, UnboundedReceiver<T>) {
+    let chan = Chan::new(None);
+    let sender = UnboundedSender(chan.clone());
+    let receiver = UnboundedReceiver(Receiver {
+        chan,
+        waiter: Waiter::default(),
+    });
+    (sender, receiver)
+}
+
+/// Sends values to a bounded [`channel`].
+///
+/// As a [`Sink`], it's ready once it reserved room for a value in the
+/// channel.
+pub struct Sender<T> {
+    chan: Arc<Chan<T>>,
+    waiter: Waiter,
+    // whether `poll_ready` reserved room for the next value
+    reserved: bool,
+}
+
+impl<T> Sender<T> {
+    /// Sends a value, waiting until there's room for it in the channel.
+    ///
+    /// Fails if the receiver was dropped or closed.
+    pub async fn send(&self, value: T) -> Result<(), SendError<T>> {
+        let mut value = Some(value);
+        let mut waiter = Waiter::default();
+        poll_fn(|cx| {
+            waiter.poll_until(&self.chan.send_notify, cx, || {
+                let Some(item) = value.take() else {
+                    unreachable!("value already sent");
+                };
+                match self.chan.try_send(item, false) {
+                    Ok(()) => Some(Ok(())),
+                    Err(TrySendError::Closed(item)) => Some(Err(SendError(item))),
+                    Err(TrySendError::Full(item)) => {
+                        value = Some(item);
+                        None
+                    }
+                }
+            })
+        })
+        .await
+    }
+
+    pub fn try_send(&self, value: T) -> Result<(), TrySendError<T>> {
+        self.chan.try_send(value, false)
+    }
+
+    /// Whether the receiver was dropped or closed.
+    pub fn is_closed(&self) -> bool {
+        self.chan.is_closed()
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.chan.clone_sender();
+        Sender {
+            chan: self.chan.clone(),
+            waiter: Waiter::default(),
+            reserved: false,
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        if self.reserved {
+            self.chan.release();
+        }
+        self.chan.drop_sender();
+    }
+}
+
+impl<T> Sink<T> for Sender<T> {
+    type Error = SendError<T>;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = &mut *self;
+        if this.reserved {
+            return Poll::Ready(Ok(()));
+        }
+        let chan = &this.chan;
+        let reserved = ready!(this
+            .waiter
+            .poll_until(&chan.send_notify, cx, || chan.reserve()));
+        this.reserved = reserved;
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, item: T) -> Result<(), Self::Error> {
+        // sends in the room reserved by `poll_ready`, unless the receiver was
+        // already closed then
+        let reserved = core::mem::take(&mut self.reserved);
+        match self.chan.try_send(item, reserved) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Closed(item)) => Err(SendError(item)),
+            Err(TrySendError::Full(_)) => panic!("`start_send` called before `poll_ready`"),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Receives values from a bounded [`channel`].
+///
+/// As a [`Stream`], it ends when the channel is closed and empty.
+pub struct Receiver<T> {
+    chan: Arc<Chan<T>>,
+    waiter: Waiter,
+}
+
+impl<T> Receiver<T> {
+    /// Receives the next value, failing once the channel is closed and empty.
+    pub async fn recv(&mut self) -> Result<T, RecvError> {
+        poll_fn(|cx| self.poll_recv(cx))
+            .await
+            .ok_or(RecvError::Closed)
+    }
+
+    pub fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        self.chan.poll_recv(&mut self.waiter, cx)
+    }
+
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        self.chan.try_recv()
+    }
+
+    /// Prevents sending more values, while still allowing to receive the
+    /// values already in the channel.
+    pub fn close(&mut self) {
+        self.chan.close();
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.chan.close();
+    }
+}
+
+impl<T> Stream for Receiver<T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        self.poll_recv(cx)
+    }
+}
+
+/// Sends values to an [`unbounded_channel`].
+pub struct UnboundedSender<T>(Arc<Chan<T>>);
+
+impl<T> UnboundedSender<T> {
+    /// Sends a value, failing if the receiver was dropped or closed.
+    pub fn send(&self, value: T) -> Result<(), SendError<T>> {
+        self.0
+            .try_send(value, false)
+            .map_err(|err| SendError(err.into_inner()))
+    }
+
+    /// Whether the receiver was dropped or closed.
+    pub fn is_closed(&self) -> bool {
+        self.0.is_closed()
+    }
+}
+
+impl<T> Clone for UnboundedSender<T> {
+    fn clone(&self) -> Self {
+        self.0.clone_sender();
+        UnboundedSender(self.0.clone())
+    }
+}
+
+impl<T> Drop for UnboundedSender<T> {
+    fn drop(&mut self) {
+        self.0.drop_sender();
+    }
+}
+
+impl<T> Sink<T> for UnboundedSender<T> {
+    type Error = SendError<T>;
+
+    fn poll_ready(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: T) -> Result<(), Self::Error> {
+        self.send(item)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Receives values from an [`unbounded_channel`].
+///
+/// As a [`Stream`], it ends when the channel is closed and empty.
+pub struct UnboundedReceiver<T>(Receiver<T>);
+
+impl<T> UnboundedReceiver<T> {
+    /// Receives the next value, failing once the channel is closed and empty.
+    pub async fn recv(&mut self) -> Result<T, RecvError> {
+        self.0.recv().await
+    }
+
+    pub fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        self.0.poll_recv(cx)
+    }
+
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        self.0.try_recv()
+    }
+
+    /// Prevents sending more values, while still allowing to receive the
+    /// values already in the channel.
+    pub fn close(&mut self) {
+        self.0.close();
+    }
+}
+
+impl<T> Stream for UnboundedReceiver<T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        self.poll_recv(cx)
+    }
+}
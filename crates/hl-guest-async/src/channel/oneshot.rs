@@ -0,0 +1,120 @@
+//! A channel to send a single value.
+
+use alloc::sync::Arc;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use spin::Mutex;
+
+use super::{RecvError, TryRecvError};
+use crate::notify::{Notify, Waiter};
+
+struct Shared<T> {
+    state: Mutex<State<T>>,
+    notify: Notify,
+}
+
+struct State<T> {
+    value: Option<T>,
+    sender_closed: bool,
+    receiver_closed: bool,
+}
+
+/// Sends the value of a [`channel`].
+pub struct Sender<T>(Arc<Shared<T>>);
+
+/// Receives the value of a [`channel`], by awaiting it.
+pub struct Receiver<T> {
+    shared: Arc<Shared<T>>,
+    waiter: Waiter,
+}
+
+pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
+    let shared = Arc::new(Shared {
+        state: Mutex::new(State {
+            value: None,
+            sender_closed: false,
+            receiver_closed: false,
+        }),
+        notify: Notify::new(),
+    });
+    let receiver = Receiver {
+        shared: shared.clone(),
+        waiter: Waiter::default(),
+    };
+    (Sender(shared), receiver)
+}
+
+impl<T> Sender<T> {
+    /// Sends the value, or returns it if the receiver was dropped.
+    pub fn send(self, value: T) -> Result<(), T> {
+        let mut state = self.0.state.lock();
+        if state.receiver_closed {
+            return Err(value);
+        }
+        state.value = Some(value);
+        // dropping `self` notifies the receiver
+        Ok(())
+    }
+
+    /// Whether the receiver was dropped or closed.
+    pub fn is_closed(&self) -> bool {
+        self.0.state.lock().receiver_closed
+    }
+
+    /// Waits until the receiver is dropped or closed.
+    pub async fn closed(&mut self) {
+        let mut waiter = Waiter::default();
+        core::future::poll_fn(|cx| {
+            waiter.poll_until(&self.0.notify, cx, || self.is_closed().then_some(()))
+        })
+        .await
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        self.0.state.lock().sender_closed = true;
+        self.0.notify.notify_waiters();
+    }
+}
+
+impl<T> Receiver<T> {
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        let mut state = self.shared.state.lock();
+        match state.value.take() {
+            Some(value) => Ok(value),
+            None if state.sender_closed => Err(TryRecvError::Closed),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    /// Prevents the sender from sending a value, without dropping the receiver.
+    pub fn close(&mut self) {
+        self.shared.state.lock().receiver_closed = true;
+        self.shared.notify.notify_waiters();
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+impl<T> Future for Receiver<T> {
+    type Output = Result<T, RecvError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let shared = &this.shared;
+        this.waiter.poll_until(&shared.notify, cx, || {
+            let mut state = shared.state.lock();
+            match state.value.take() {
+                Some(value) => Some(Ok(value)),
+                None if state.sender_closed => Some(Err(RecvError::Closed)),
+                None => None,
+            }
+        })
+    }
+}
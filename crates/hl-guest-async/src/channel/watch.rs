@@ -0,0 +1,217 @@
+//! A channel holding a single value that receivers can watch for changes.
+//!
+//! Receivers only see the latest value: intermediate values sent before a
+//! receiver looks at the channel are never seen by it.
+
+use alloc::sync::Arc;
+use core::future::poll_fn;
+use core::ops::Deref;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use futures::Stream;
+use spin::{Mutex, MutexGuard};
+
+use super::{RecvError, SendError};
+use crate::notify::{Notify, Waiter};
+
+struct Shared<T> {
+    value: Mutex<T>,
+    state: Mutex<State>,
+    // notified when the value changes, or the sender is dropped
+    notify: Notify,
+}
+
+struct State {
+    // incremented every time the value changes
+    version: u64,
+    sender_closed: bool,
+    receivers: usize,
+}
+
+/// Creates a channel holding `init`.
+pub fn channel<T>(init: T) -> (Sender<T>, Receiver<T>) {
+    let shared = Arc::new(Shared {
+        value: Mutex::new(init),
+        state: Mutex::new(State {
+            version: 0,
+            sender_closed: false,
+            receivers: 1,
+        }),
+        notify: Notify::new(),
+    });
+    let receiver = Receiver {
+        shared: shared.clone(),
+        seen: 0,
+        waiter: Waiter::default(),
+    };
+    (Sender(shared), receiver)
+}
+
+/// A reference to the value of a watch [`channel`].
+///
+/// The channel is locked while it's alive, so don't hold it across an
+/// `.await`.
+pub struct Ref<'a, T>(MutexGuard<'a, T>);
+
+impl<T> Deref for Ref<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+/// Updates the value of a watch [`channel`].
+pub struct Sender<T>(Arc<Shared<T>>);
+
+impl<T> Sender<T> {
+    /// Replaces the value, failing if there are no receivers.
+    pub fn send(&self, value: T) -> Result<(), SendError<T>> {
+        if self.0.state.lock().receivers == 0 {
+            return Err(SendError(value));
+        }
+        self.send_replace(value);
+        Ok(())
+    }
+
+    /// Replaces the value even if there are no receivers, and returns the
+    /// previous one.
+    pub fn send_replace(&self, value: T) -> T {
+        let previous = core::mem::replace(&mut *self.0.value.lock(), value);
+        self.changed();
+        previous
+    }
+
+    /// Modifies the value in place, and notifies the receivers.
+    pub fn send_modify(&self, modify: impl FnOnce(&mut T)) {
+        modify(&mut self.0.value.lock());
+        self.changed();
+    }
+
+    fn changed(&self) {
+        self.0.state.lock().version += 1;
+        self.0.notify.notify_waiters();
+    }
+
+    pub fn borrow(&self) -> Ref<'_, T> {
+        Ref(self.0.value.lock())
+    }
+
+    /// Creates a receiver that has seen the current value.
+    pub fn subscribe(&self) -> Receiver<T> {
+        let mut state = self.0.state.lock();
+        state.receivers += 1;
+        Receiver {
+            shared: self.0.clone(),
+            seen: state.version,
+            waiter: Waiter::default(),
+        }
+    }
+
+    pub fn receiver_count(&self) -> usize {
+        self.0.state.lock().receivers
+    }
+
+    /// Whether all the receivers were dropped.
+    pub fn is_closed(&self) -> bool {
+        self.receiver_count() == 0
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        self.0.state.lock().sender_closed = true;
+        self.0.notify.notify_waiters();
+    }
+}
+
+/// Watches the value of a watch [`channel`].
+///
+/// As a [`Stream`], it yields the value every time it changes, and ends when
+/// the sender is dropped.
+pub struct Receiver<T> {
+    shared: Arc<Shared<T>>,
+    // version of the last value seen
+    seen: u64,
+    waiter: Waiter,
+}
+
+impl<T> Receiver<T> {
+    /// Returns the current value, without marking it as seen.
+    pub fn borrow(&self) -> Ref<'_, T> {
+        Ref(self.shared.value.lock())
+    }
+
+    /// Returns the current value, and marks it as seen.
+    pub fn borrow_and_update(&mut self) -> Ref<'_, T> {
+        // lock the value first, so that it can't change after the version
+        // is read
+        let value = self.shared.value.lock();
+        self.seen = self.shared.state.lock().version;
+        Ref(value)
+    }
+
+    /// Whether the value changed since it was last seen.
+    ///
+    /// Fails if the sender was dropped.
+    pub fn has_changed(&self) -> Result<bool, RecvError> {
+        let state = self.shared.state.lock();
+        if state.sender_closed {
+            return Err(RecvError::Closed);
+        }
+        Ok(state.version != self.seen)
+    }
+
+    /// Waits until the value changes from the last seen one, and marks it as
+    /// seen.
+    ///
+    /// Fails if the sender was dropped.
+    pub async fn changed(&mut self) -> Result<(), RecvError> {
+        poll_fn(|cx| self.poll_changed(cx)).await
+    }
+
+    fn poll_changed(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), RecvError>> {
+        let shared = &self.shared;
+        let seen = &mut self.seen;
+        self.waiter.poll_until(&shared.notify, cx, || {
+            let state = shared.state.lock();
+            if state.version != *seen {
+                *seen = state.version;
+                Some(Ok(()))
+            } else if state.sender_closed {
+                Some(Err(RecvError::Closed))
+            } else {
+                None
+            }
+        })
+    }
+}
+
+impl<T> Clone for Receiver<T> {
+    fn clone(&self) -> Self {
+        self.shared.state.lock().receivers += 1;
+        Receiver {
+            shared: self.shared.clone(),
+            seen: self.seen,
+            waiter: Waiter::default(),
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.shared.state.lock().receivers -= 1;
+    }
+}
+
+impl<T: Clone> Stream for Receiver<T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        match self.poll_changed(cx) {
+            Poll::Ready(Ok(())) => Poll::Ready(Some(self.borrow().clone())),
+            Poll::Ready(Err(RecvError::Closed)) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
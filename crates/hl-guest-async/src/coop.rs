@@ -0,0 +1,62 @@
+//! Cooperative scheduling.
+//!
+//! Every time the runtime polls a task, the task gets a budget of operations
+//! on the runtime's resources (channels, locks, timers...). Once it's spent,
+//! those operations return `Pending` even if they are ready, and wake the
+//! task right away, so a task that is always ready still yields to the others.
+
+use core::future::poll_fn;
+use core::sync::atomic::{AtomicU32, Ordering};
+use core::task::{Context, Poll};
+
+const BUDGET: u32 = 128;
+
+// Remaining budget of the task being polled, or `UNCONSTRAINED` outside of
+// the runtime's tasks
+static REMAINING: AtomicU32 = AtomicU32::new(UNCONSTRAINED);
+const UNCONSTRAINED: u32 = u32::MAX;
+
+/// Runs `f`, which polls a task, with a fresh budget.
+pub(crate) fn with_budget<R>(f: impl FnOnce() -> R) -> R {
+    let previous = REMAINING.swap(BUDGET, Ordering::Relaxed);
+    let result = f();
+    REMAINING.store(previous, Ordering::Relaxed);
+    result
+}
+
+/// Spends one unit of the budget, or returns `Pending` and wakes the task
+/// if it's spent.
+pub(crate) fn poll_proceed(cx: &mut Context<'_>) -> Poll<()> {
+    let remaining = REMAINING.load(Ordering::Relaxed);
+    if remaining == UNCONSTRAINED {
+        return Poll::Ready(());
+    }
+    if remaining == 0 {
+        cx.waker().wake_by_ref();
+        return Poll::Pending;
+    }
+    REMAINING.store(remaining - 1, Ordering::Relaxed);
+    Poll::Ready(())
+}
+
+/// Spends one unit of the task's budget, yielding to other tasks if it's
+/// spent.
+///
+/// Call it in loops that don't otherwise await the runtime's resources.
+pub async fn consume_budget() {
+    poll_fn(poll_proceed).await
+}
+
+/// Yields to the other tasks once.
+pub async fn yield_now() {
+    let mut yielded = false;
+    poll_fn(|cx| {
+        if yielded {
+            return Poll::Ready(());
+        }
+        yielded = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    })
+    .await
+}
@@ -0,0 +1,57 @@
+//! Introspection of the runtime, to debug tasks that don't make progress.
+
+use alloc::vec::Vec;
+use core::fmt;
+use core::panic::Location;
+use core::time::Duration;
+
+use crate::runtime::Runtime;
+
+/// The state of the runtime at some point.
+#[derive(Clone, Debug)]
+pub struct Snapshot {
+    /// The spawned tasks that didn't complete, in no particular order.
+    pub tasks: Vec<TaskSnapshot>,
+    /// The time left until each scheduled timer fires, soonest first.
+    pub timers: Vec<Duration>,
+    /// The file descriptors the tasks wait on.
+    pub fds: Vec<i32>,
+}
+
+/// A spawned task that didn't complete.
+#[derive(Clone, Debug)]
+pub struct TaskSnapshot {
+    /// A unique id, in the order the tasks were spawned.
+    pub id: u64,
+    /// Where the task was spawned.
+    pub spawned_at: &'static Location<'static>,
+    /// Whether the task is waiting to be polled, rather than waiting to be
+    /// woken up.
+    pub ready: bool,
+}
+
+/// Takes a snapshot of the runtime.
+pub fn snapshot() -> Snapshot {
+    Runtime::global().snapshot()
+}
+
+impl fmt::Display for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} tasks:", self.tasks.len())?;
+        for task in &self.tasks {
+            writeln!(f, "  {task}")?;
+        }
+        writeln!(f, "{} timers:", self.timers.len())?;
+        for timer in &self.timers {
+            writeln!(f, "  in {timer:?}")?;
+        }
+        write!(f, "waiting on fds: {:?}", self.fds)
+    }
+}
+
+impl fmt::Display for TaskSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = if self.ready { "ready" } else { "waiting" };
+        write!(f, "task {} spawned at {} ({state})", self.id, self.spawned_at)
+    }
+}
@@ -0,0 +1,113 @@
+//! Events pushed by the host to the guest.
+//!
+//! Each event has a name and some data. Subscribing to a name returns a
+//! stream of the data of the events with that name.
+
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString as _};
+use alloc::vec::Vec;
+use core::future::{poll_fn, Future};
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use futures::Stream;
+use spin::Mutex;
+
+use crate::channel::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use crate::host::next_event;
+use crate::runtime::{IoReady, Runtime};
+use crate::sync::cancel_host;
+
+/// The file descriptor the runtime polls to know when the host has queued
+/// events. It's not a real file descriptor.
+#[doc(hidden)]
+pub const EVENTS_FD: i32 = i32::MAX;
+
+/// The event the host sends to cancel [`CancellationToken::host`].
+///
+/// [`CancellationToken::host`]: crate::sync::CancellationToken::host
+#[doc(hidden)]
+pub const CANCEL_EVENT: &str = "__Cancel";
+
+static SUBSCRIBERS: Mutex<BTreeMap<String, Vec<UnboundedSender<Vec<u8>>>>> =
+    Mutex::new(BTreeMap::new());
+
+/// Subscribes to the events named `name`.
+///
+/// Events are only delivered to the subscriptions that exist when the guest
+/// takes them from the host, events nobody subscribed to are dropped.
+pub fn subscribe(name: &str) -> Subscription {
+    let (sender, receiver) = unbounded_channel();
+    SUBSCRIBERS
+        .lock()
+        .entry(name.to_string())
+        .or_default()
+        .push(sender);
+    Subscription {
+        receiver,
+        ready: None,
+    }
+}
+
+// Takes all the events queued by the host, and sends them to their
+// subscribers
+pub(crate) fn dispatch() {
+    while let Some((name, data)) = next_event() {
+        if name == CANCEL_EVENT {
+            cancel_host();
+        }
+        let mut subscribers = SUBSCRIBERS.lock();
+        let Some(senders) = subscribers.get_mut(&name) else {
+            continue;
+        };
+        senders.retain(|sender| sender.send(data.clone()).is_ok());
+        if senders.is_empty() {
+            subscribers.remove(&name);
+        }
+    }
+}
+
+/// A stream of the data of the events with a given name, returned by
+/// [`subscribe`].
+///
+/// Dropping it unsubscribes.
+pub struct Subscription {
+    receiver: UnboundedReceiver<Vec<u8>>,
+    // notified when the host has queued events
+    ready: Option<IoReady>,
+}
+
+impl Subscription {
+    /// Waits for the next event, and returns its data.
+    pub async fn recv(&mut self) -> Vec<u8> {
+        // the senders are never dropped while the subscription exists
+        poll_fn(|cx| self.poll_recv(cx)).await
+    }
+
+    pub fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Vec<u8>> {
+        loop {
+            dispatch();
+            // also wakes us up if another subscription takes the events
+            if let Poll::Ready(Some(data)) = self.receiver.poll_recv(cx) {
+                self.ready = None;
+                return Poll::Ready(data);
+            }
+            match &mut self.ready {
+                None => self.ready = Some(Runtime::global().schedule_io(EVENTS_FD)),
+                Some(ready) => {
+                    if Pin::new(ready).poll(cx).is_pending() {
+                        return Poll::Pending;
+                    }
+                    self.ready = None;
+                }
+            }
+        }
+    }
+}
+
+impl Stream for Subscription {
+    type Item = Vec<u8>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Vec<u8>>> {
+        self.get_mut().poll_recv(cx).map(Some)
+    }
+}
@@ -1,3 +1,5 @@
+use alloc::string::String;
+use alloc::vec::Vec;
 use core::time::Duration;
 
 use crate::io::{Error, Result};
@@ -9,18 +11,30 @@ struct __timespec {
     tv_nsec: i64,
 }
 
+// A `Vec<u8>` allocated by the caller, whose ownership is passed to us
+#[repr(C)]
+#[allow(non_camel_case_types)]
+struct __buffer {
+    ptr: *mut u8,
+    len: usize,
+    capacity: usize,
+}
+
 extern "C" {
-    fn __unixtime() -> __timespec;
+    fn __monotonic() -> __timespec;
     fn __try_read(fd: i32, buffer: *mut u8, len: usize) -> i32;
     fn __poll_read(fds: *mut i32, nfds: usize, timeout: __timespec) -> i32;
-    fn __sleep(timeout: __timespec);
+    fn __next_event() -> __buffer;
 }
 
+/// The time of the host's monotonic clock, which never goes backwards.
 pub fn get_time() -> Duration {
-    let ts = unsafe { __unixtime() };
+    let ts = unsafe { __monotonic() };
     Duration::new(ts.tv_sec as _, ts.tv_nsec as _)
 }
 
+/// Reads what's available on `fd`, returns 0 once it's closed, and
+/// [`Error::Again`] if there's nothing to read yet.
 pub fn try_read(fd: i32, buf: &mut [u8]) -> Result<usize> {
     let ret = unsafe { __try_read(fd, buf.as_mut_ptr(), buf.len()) };
     match ret {
@@ -37,7 +51,12 @@ pub fn poll_read(mut fds: impl AsMut<[i32]>, timeout: Option<Duration>) -> Resul
         }
         return Ok(0);
     }
-    let timeout = timeout.unwrap_or_default();
+    // the host counts whole microseconds, and waits forever for zero: round
+    // up, so that a timer due in less than a microsecond still fires
+    let timeout = match timeout {
+        Some(timeout) => Duration::from_micros(timeout.as_nanos().div_ceil(1000) as u64),
+        None => Duration::ZERO,
+    };
     let timeout = __timespec {
         tv_sec: timeout.as_secs() as _,
         tv_nsec: timeout.subsec_nanos() as _,
@@ -50,14 +69,13 @@ pub fn poll_read(mut fds: impl AsMut<[i32]>, timeout: Option<Duration>) -> Resul
     Ok(ret as _)
 }
 
-pub fn sleep(duration: Option<Duration>) {
-    if let Some(Duration::ZERO) = duration {
-        return;
-    }
-    let duration = duration.unwrap_or_default();
-    let duration = __timespec {
-        tv_sec: duration.as_secs() as _,
-        tv_nsec: duration.subsec_nanos() as _,
-    };
-    unsafe { __sleep(duration) }
+/// Takes the next event queued by the host, if any, as its name and data.
+pub fn next_event() -> Option<(String, Vec<u8>)> {
+    let buffer = unsafe { __next_event() };
+    let mut buffer = unsafe { Vec::from_raw_parts(buffer.ptr, buffer.len, buffer.capacity) };
+    // the length of the name as a little endian u32, the name, and the data
+    let name_len = u32::from_le_bytes(buffer.get(..4)?.try_into().ok()?) as usize;
+    let data = buffer.split_off(4 + name_len.min(buffer.len() - 4));
+    let name = String::from_utf8_lossy(&buffer[4..]).into_owned();
+    Some((name, data))
 }
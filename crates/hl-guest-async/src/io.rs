@@ -1,8 +1,12 @@
 use alloc::{string::String, vec::Vec};
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{ready, Context, Poll};
+use futures::Stream;
 use spin::{Lazy, Mutex, MutexGuard};
 
 use crate::host::try_read;
-use crate::runtime::Runtime;
+use crate::runtime::{IoReady, Runtime};
 
 #[derive(Clone, Copy, Debug)]
 #[non_exhaustive]
@@ -13,6 +17,7 @@ pub enum Error {
 
 pub type Result<T> = core::result::Result<T, Error>;
 
+/// Reads from stdin, or returns 0 once it's closed.
 pub async fn read(buf: &mut [u8]) -> Result<usize> {
     loop {
         match try_read(0, buf) {
@@ -64,6 +69,95 @@ impl Stdin {
     pub async fn read_line_to_string(&self) -> Result<String> {
         self.lock().read_line_to_string().await
     }
+
+    /// Returns a [`Stream`] of the lines read from stdin, that ends once
+    /// stdin is closed.
+    pub fn lines(self) -> Lines {
+        Lines {
+            inner: self.inner,
+            line: Vec::new(),
+            ready: None,
+        }
+    }
+}
+
+/// A [`Stream`] of the lines read from stdin, without their line ending,
+/// returned by [`Stdin::lines`].
+///
+/// Stdin is only locked while the stream is polled, so other readers can
+/// take their turn while it waits for input.
+pub struct Lines {
+    inner: &'static Mutex<StdinInner>,
+    // the start of the line being read
+    line: Vec<u8>,
+    // the wait for more input
+    ready: Option<IoReady>,
+}
+
+impl Lines {
+    // Takes the line read so far, without its line ending
+    fn take_line(&mut self) -> String {
+        let mut line = core::mem::take(&mut self.line);
+        if line.ends_with(b"\n") {
+            line.pop();
+            if line.ends_with(b"\r") {
+                line.pop();
+            }
+        }
+        String::from_utf8_lossy(&line).into_owned()
+    }
+}
+
+impl Stream for Lines {
+    type Item = Result<String>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<String>>> {
+        loop {
+            if let Some(ready) = &mut self.ready {
+                ready!(Pin::new(ready).poll(cx));
+                self.ready = None;
+            }
+
+            // a `StdinLock` can be held across an `.await`, let its owner
+            // run rather than spinning
+            let Some(mut inner) = self.inner.try_lock() else {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            };
+            let buffer = &mut inner.buffer;
+            if buffer.is_empty() {
+                buffer.resize(1024, 0);
+                let read = try_read(0, buffer);
+                buffer.truncate(*read.as_ref().unwrap_or(&0));
+                match read {
+                    Ok(0) => {
+                        drop(inner);
+                        // the last line may not end with a line ending
+                        if self.line.is_empty() {
+                            return Poll::Ready(None);
+                        }
+                        return Poll::Ready(Some(Ok(self.take_line())));
+                    }
+                    Ok(_) => {}
+                    Err(Error::Again) => {
+                        drop(inner);
+                        self.ready = Some(Runtime::global().schedule_io(0));
+                        continue;
+                    }
+                    Err(e) => return Poll::Ready(Some(Err(e))),
+                }
+            }
+
+            let end = buffer.iter().position(|c| *c == b'\n');
+            let end = end.map_or(buffer.len(), |end| end + 1);
+            let bytes: Vec<u8> = buffer.drain(..end).collect();
+            drop(inner);
+            self.line.extend_from_slice(&bytes);
+            if self.line.ends_with(b"\n") {
+                return Poll::Ready(Some(Ok(self.take_line())));
+            }
+        }
+    }
 }
 
 impl StdinLock<'_> {
@@ -71,8 +165,11 @@ impl StdinLock<'_> {
         let buffer = &mut self.inner.buffer;
         while buffer.is_empty() {
             buffer.resize(1024, 0);
-            let n = read(buffer).await?;
-            buffer.truncate(n);
+            let n = read(buffer).await;
+            buffer.truncate(*n.as_ref().unwrap_or(&0));
+            if n? == 0 {
+                return Ok(0);
+            }
         }
 
         let n = core::cmp::min(buf.len(), buffer.len());
@@ -86,10 +183,12 @@ impl StdinLock<'_> {
         let mut bytes = alloc::vec![];
         loop {
             let mut c = 0u8;
-            // our implementation of read stops at newline
-            self.read(core::slice::from_mut(&mut c)).await?;
+            // stdin is closed, the last line may not end with a line ending
+            if self.read(core::slice::from_mut(&mut c)).await? == 0 {
+                break;
+            }
             bytes.push(c);
-            if c == 0 || c == b'\n' {
+            if c == b'\n' {
                 break;
             }
         }
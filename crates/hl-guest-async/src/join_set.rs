@@ -0,0 +1,91 @@
+use core::future::{poll_fn, Future};
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use futures::stream::{FuturesUnordered, Stream};
+
+use crate::runtime::{AbortHandle, JoinError, JoinHandle, Runtime};
+
+/// A collection of spawned tasks, that can be joined in the order they
+/// complete.
+///
+/// Dropping the set aborts all the tasks in it.
+pub struct JoinSet<T> {
+    tasks: FuturesUnordered<JoinHandle<T>>,
+}
+
+impl<T> JoinSet<T> {
+    pub fn new() -> Self {
+        JoinSet {
+            tasks: FuturesUnordered::new(),
+        }
+    }
+
+    /// The number of tasks in the set, including completed tasks that
+    /// weren't joined yet.
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    /// Spawns a task in the set.
+    #[track_caller]
+    pub fn spawn(&mut self, future: impl Future<Output = T> + Send + 'static) -> AbortHandle
+    where
+        T: Send + 'static,
+    {
+        let handle = Runtime::global().spawn(future);
+        let abort = handle.abort_handle();
+        self.tasks.push(handle);
+        abort
+    }
+
+    /// Spawns a task that is not `Send` in the set.
+    #[track_caller]
+    pub fn spawn_local(&mut self, future: impl Future<Output = T> + 'static) -> AbortHandle
+    where
+        T: 'static,
+    {
+        let handle = Runtime::global().spawn_local(future);
+        let abort = handle.abort_handle();
+        self.tasks.push(handle);
+        abort
+    }
+
+    /// Waits for any of the tasks to complete and returns its result, or
+    /// `None` if the set is empty.
+    pub async fn join_next(&mut self) -> Option<Result<T, JoinError>> {
+        poll_fn(|cx| self.poll_join_next(cx)).await
+    }
+
+    pub fn poll_join_next(&mut self, cx: &mut Context<'_>) -> Poll<Option<Result<T, JoinError>>> {
+        Pin::new(&mut self.tasks).poll_next(cx)
+    }
+
+    /// Aborts all the tasks. They stay in the set until they are joined.
+    pub fn abort_all(&mut self) {
+        for task in self.tasks.iter() {
+            task.abort();
+        }
+    }
+
+    /// Aborts all the tasks, and waits for them to complete.
+    pub async fn shutdown(&mut self) {
+        self.abort_all();
+        while self.join_next().await.is_some() {}
+    }
+}
+
+impl<T> Default for JoinSet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for JoinSet<T> {
+    fn drop(&mut self) {
+        self.abort_all();
+    }
+}
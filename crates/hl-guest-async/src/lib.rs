@@ -8,19 +8,61 @@ extern crate alloc;
 #[doc(hidden)]
 mod host;
 pub mod channel;
+mod coop;
+pub mod debug;
+pub mod events;
 pub mod io;
+mod join_set;
 pub mod notify;
 mod runtime;
+pub mod stream;
+pub mod sync;
+pub mod task_local;
 pub mod time;
 
 use core::future::Future;
+use core::time::Duration;
 
-pub use runtime::JoinHandle;
+/// Polls several futures concurrently and returns all their outputs.
+pub use futures::join;
+/// Waits on several futures and runs the branch of the first one to complete.
+///
+/// The branches are polled in order, as there's no source of randomness to
+/// pick them fairly. The futures must be fused, see [`FutureExt::fuse`].
+pub use futures::select_biased as select;
+/// Like [`join!`], but returns early with the first error.
+pub use futures::try_join;
+pub use futures::FutureExt;
 
+pub use coop::{consume_budget, yield_now};
+pub use join_set::JoinSet;
+pub use runtime::{AbortHandle, JoinError, JoinHandle};
+pub use stream::{Stream, StreamExt};
+
+#[track_caller]
 pub fn block_on<T>(future: impl Future<Output = T>) -> T {
     runtime::Runtime::global().block_on(future)
 }
 
+#[track_caller]
 pub fn spawn<T: Send + 'static>(future: impl Future<Output = T> + Send + 'static) -> JoinHandle<T> {
     runtime::Runtime::global().spawn(future)
 }
+
+#[track_caller]
+pub fn spawn_local<T: 'static>(future: impl Future<Output = T> + 'static) -> JoinHandle<T> {
+    runtime::Runtime::global().spawn_local(future)
+}
+
+/// Prepares the runtime for a new call from the host, called by `hl-guest`
+/// before each guest function.
+#[doc(hidden)]
+pub fn begin_call() {
+    sync::reset_host();
+}
+
+/// Runs the spawned tasks for up to `budget`, and returns the number of tasks
+/// still running, or 0 if none of them can make progress until the next call.
+pub fn run_pending(budget: Duration) -> usize {
+    runtime::Runtime::global().run_pending(budget)
+}
@@ -6,9 +6,10 @@ use core::task::Waker;
 use alloc::collections::VecDeque;
 use alloc::sync::Arc;
 use core::task::{Context, Poll};
-use futures::task::noop_waker;
 use spin::Mutex;
 
+use crate::coop;
+
 #[derive(Default)]
 pub struct Notify {
     waiters: Mutex<VecDeque<NotifiedInner>>,
@@ -18,16 +19,33 @@ pub struct Notified {
     inner: NotifiedInner,
 }
 
+// The state shared between a `Notified` and its `Notify`, in a single
+// allocation
 #[derive(Clone)]
-pub struct NotifiedInner {
-    notified: Arc<AtomicBool>,
-    cancelled: Arc<AtomicBool>,
-    waker: Arc<Mutex<Waker>>,
+pub struct NotifiedInner(Arc<NotifiedState>);
+
+struct NotifiedState {
+    notified: AtomicBool,
+    cancelled: AtomicBool,
+    waker: Mutex<Option<Waker>>,
+}
+
+impl NotifiedInner {
+    fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::SeqCst)
+    }
+
+    fn notify(&self) {
+        self.0.notified.store(true, Ordering::SeqCst);
+        if let Some(waker) = self.0.waker.lock().take() {
+            waker.wake();
+        }
+    }
 }
 
 impl Drop for Notified {
     fn drop(&mut self) {
-        self.inner.cancelled.store(true, Ordering::SeqCst);
+        self.inner.0.cancelled.store(true, Ordering::SeqCst);
     }
 }
 
@@ -39,9 +57,8 @@ impl Notify {
     pub fn notify_one(&self) {
         let mut waiters = self.waiters.lock();
         while let Some(waiter) = waiters.pop_front() {
-            if !waiter.cancelled.load(Ordering::SeqCst) {
-                waiter.notified.store(true, Ordering::SeqCst);
-                waiter.waker.lock().wake_by_ref();
+            if !waiter.is_cancelled() {
+                waiter.notify();
                 break;
             }
         }
@@ -50,9 +67,8 @@ impl Notify {
     pub fn notify_last(&self) {
         let mut waiters = self.waiters.lock();
         while let Some(waiter) = waiters.pop_back() {
-            if !waiter.cancelled.load(Ordering::SeqCst) {
-                waiter.notified.store(true, Ordering::SeqCst);
-                waiter.waker.lock().wake_by_ref();
+            if !waiter.is_cancelled() {
+                waiter.notify();
                 break;
             }
         }
@@ -61,19 +77,18 @@ impl Notify {
     pub fn notify_waiters(&self) {
         let mut waiters = self.waiters.lock();
         while let Some(waiter) = waiters.pop_front() {
-            if !waiter.cancelled.load(Ordering::SeqCst) {
-                waiter.notified.store(true, Ordering::SeqCst);
-                waiter.waker.lock().wake_by_ref();
+            if !waiter.is_cancelled() {
+                waiter.notify();
             }
         }
     }
 
     pub fn notified(&self) -> Notified {
-        let inner = NotifiedInner {
-            notified: Arc::new(AtomicBool::new(false)),
-            cancelled: Arc::new(AtomicBool::new(false)),
-            waker: Arc::new(Mutex::new(noop_waker())),
-        };
+        let inner = NotifiedInner(Arc::new(NotifiedState {
+            notified: AtomicBool::new(false),
+            cancelled: AtomicBool::new(false),
+            waker: Mutex::new(None),
+        }));
         self.waiters.lock().push_back(inner.clone());
         Notified { inner }
     }
@@ -83,16 +98,56 @@ impl Future for Notified {
     type Output = ();
 
     fn poll(self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<()> {
-        if self.inner.notified.load(Ordering::SeqCst) {
-            self.inner.notified.store(false, Ordering::SeqCst);
+        let state = &self.inner.0;
+        if state.notified.load(Ordering::SeqCst) {
+            state.notified.store(false, Ordering::SeqCst);
             return Poll::Ready(());
         }
 
-        let mut waker = self.inner.waker.lock();
-        if !waker.will_wake(ctx.waker()) {
-            *waker = ctx.waker().clone();
+        let mut waker = state.waker.lock();
+        match &mut *waker {
+            Some(waker) if waker.will_wake(ctx.waker()) => {}
+            waker => *waker = Some(ctx.waker().clone()),
         }
 
         Poll::Pending
     }
 }
+
+/// Waits on a [`Notify`] until a condition holds, from a `poll` function.
+#[derive(Default)]
+pub(crate) struct Waiter {
+    notified: Option<Notified>,
+}
+
+impl Waiter {
+    /// Returns the value of `check` as soon as it returns `Some`.
+    ///
+    /// The waiter is registered in `notify` before checking again, so a
+    /// notification between the check and the registration isn't lost.
+    pub(crate) fn poll_until<R>(
+        &mut self,
+        notify: &Notify,
+        cx: &mut Context<'_>,
+        mut check: impl FnMut() -> Option<R>,
+    ) -> Poll<R> {
+        if coop::poll_proceed(cx).is_pending() {
+            return Poll::Pending;
+        }
+        loop {
+            if let Some(result) = check() {
+                self.notified = None;
+                return Poll::Ready(result);
+            }
+            match &mut self.notified {
+                None => self.notified = Some(notify.notified()),
+                Some(notified) => {
+                    if Pin::new(notified).poll(cx).is_pending() {
+                        return Poll::Pending;
+                    }
+                    self.notified = None;
+                }
+            }
+        }
+    }
+}
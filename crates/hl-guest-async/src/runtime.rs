@@ -1,53 +1,128 @@
+use alloc::collections::VecDeque;
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use core::fmt;
 use core::future::Future;
-use core::pin::Pin;
-use core::task::{Context, Poll};
+use core::marker::PhantomData;
+use core::panic::Location;
+use core::pin::{pin, Pin};
+use core::sync::atomic::{AtomicU64, Ordering};
+use core::task::{Context, Poll, Waker};
 use core::time::Duration;
-use futures::future::BoxFuture;
-use futures::{select_biased, FutureExt};
 use spin::{Mutex, Once};
 
 mod task;
 mod work;
 
-use crate::channel::{channel, Receiver, Sender};
-use crate::notify::{Notified, Notify};
-use task::Task;
+use crate::coop;
+use crate::debug::Snapshot;
+use crate::host::get_time;
+use task::{BlockOnTask, Join, Local, Runnable, Task, Tasks};
+pub(crate) use work::{IoReady, Timer};
 
 pub struct Runtime {
-    scheduled: Receiver<Task>,
-    sender: Sender<Task>,
+    // Tasks ready to be polled. The queue keeps its capacity, so scheduling
+    // a task doesn't allocate once it has grown to the number of tasks.
+    ready: Mutex<VecDeque<Arc<dyn Runnable>>>,
+    // the spawned tasks that didn't complete yet
+    tasks: Mutex<Tasks>,
+    next_task_id: AtomicU64,
     work: Mutex<work::RuntimeWork>,
 }
 
 impl Runtime {
     fn new() -> Runtime {
-        let (sender, scheduled) = channel();
         Runtime {
-            scheduled,
-            sender,
+            ready: Mutex::default(),
+            tasks: Mutex::default(),
+            next_task_id: AtomicU64::new(0),
             work: Mutex::default(),
         }
     }
 
+    /// Runs the tasks until `future` completes.
+    ///
+    /// # Panics
+    ///
+    /// If `future` can never complete, as neither it nor the tasks are ready
+    /// and they don't wait on a timer or input, with a [`Snapshot`] of the
+    /// runtime.
+    #[track_caller]
     pub fn block_on<T>(&self, future: impl Future<Output = T>) -> T {
-        let mut future = future.fuse().boxed_local();
-        Task::block_on(&self.sender);
+        let called_at = Location::caller();
+        let mut future = pin!(future);
+        let main = Arc::new(BlockOnTask::new());
+        let waker = Waker::from(main.clone());
+        let mut cx = Context::from_waker(&waker);
         loop {
-            loop {
-                let Some(task) = self.scheduled.try_recv() else {
-                    break;
-                };
-                match task {
-                    Task::Spawn(t) => t.poll(),
-                    Task::BlockOn(t) => {
-                        if let Poll::Ready(val) = t.poll(&mut future) {
-                            return val;
-                        }
-                    }
-                };
+            if main.take_woken() {
+                if let Poll::Ready(val) = coop::with_budget(|| future.as_mut().poll(&mut cx)) {
+                    return val;
+                }
+            }
+
+            self.run_ready();
+
+            if self.ready.lock().is_empty() && !main.is_woken() {
+                let mut work = self.work.lock();
+                if !work.is_waiting() {
+                    drop(work);
+                    panic!(
+                        "deadlock: the future of block_on at {called_at} can never complete, \
+                         nothing is ready nor waiting on a timer or input\n{}",
+                        self.snapshot()
+                    );
+                }
+                work.work(None);
             }
+        }
+    }
+
+    /// Runs the spawned tasks for up to `budget`, without a future to block
+    /// on, so that they make progress between calls to `block_on`.
+    ///
+    /// Returns the number of tasks that didn't complete, or 0 if none of
+    /// them can make progress before the next call, as they are waiting on
+    /// each other rather than on a timer or input.
+    pub fn run_pending(&self, budget: Duration) -> usize {
+        let deadline = get_time() + budget;
+        loop {
+            self.run_ready();
+
+            let live = self.tasks.lock().len();
+            if live == 0 {
+                return 0;
+            }
+            if !self.ready.lock().is_empty() {
+                let now = get_time();
+                if now >= deadline {
+                    return live;
+                }
+                continue;
+            }
+
             let mut work = self.work.lock();
-            work.work();
+            if !work.is_waiting() {
+                // nothing can wake the tasks up
+                return 0;
+            }
+            let now = get_time();
+            if now >= deadline {
+                return live;
+            }
+            work.work(Some(deadline - now));
+        }
+    }
+
+    // Only runs the tasks that are ready now, so that the caller gets its
+    // turn even if they keep waking each other
+    fn run_ready(&self) {
+        let ready = self.ready.lock().len();
+        for _ in 0..ready {
+            let Some(task) = self.ready.lock().pop_front() else {
+                break;
+            };
+            task.run();
         }
     }
 
@@ -59,54 +134,164 @@ impl Runtime {
     /// Spawn a future onto the runtime.
     ///
     /// The given future is wrapped with the `Task` harness and pushed into the
-    /// `scheduled` queue. The future will be executed when `run` is called.
+    /// `ready` queue. The future will be executed when `block_on` is called.
+    #[track_caller]
     pub fn spawn<T: Send + 'static>(
         &self,
         future: impl Future<Output = T> + Send + 'static,
     ) -> JoinHandle<T> {
-        let (tx, rx) = channel();
-        let notify = Notify::new();
-        let notified = notify.notified();
-        Task::spawn(
-            async move {
-                let result = select_biased! {
-                    _ = notified.fuse() => None,
-                    result = future.fuse() => Some(result),
-                };
-                tx.send(result);
-            },
-            &self.sender,
-        );
         JoinHandle {
-            result: async move { rx.recv().await }.boxed(),
-            abort: notify,
+            task: Task::spawn(future, Location::caller()),
+            _output: PhantomData,
         }
     }
 
-    pub(crate) fn schedule_timer(&self, deadline: Duration) -> Notified {
+    /// Spawn a future that is not `Send` onto the runtime.
+    ///
+    /// Like [`Runtime::spawn`], but the future can hold `!Send` state like
+    /// `Rc` or `RefCell` across `.await`s.
+    #[track_caller]
+    pub fn spawn_local<T: 'static>(
+        &self,
+        future: impl Future<Output = T> + 'static,
+    ) -> JoinHandle<T> {
+        JoinHandle {
+            task: Task::spawn(Local::new(future), Location::caller()),
+            _output: PhantomData,
+        }
+    }
+
+    fn schedule(&self, task: Arc<dyn Runnable>) {
+        self.ready.lock().push_back(task);
+    }
+
+    // Returns the id and the slot of a new task
+    fn register(&self, task: Weak<dyn Runnable>) -> (u64, usize) {
+        let id = self.next_task_id.fetch_add(1, Ordering::SeqCst);
+        (id, self.tasks.lock().insert(task))
+    }
+
+    fn unregister(&self, slot: usize) {
+        self.tasks.lock().remove(slot);
+    }
+
+    pub(crate) fn snapshot(&self) -> Snapshot {
+        // the tasks are only dropped once the lock is released, as dropping
+        // one unregisters it
+        let tasks: Vec<_> = self.tasks.lock().iter().collect();
+        let now = get_time();
+        let work = self.work.lock();
+        Snapshot {
+            tasks: tasks.iter().map(|task| task.snapshot()).collect(),
+            timers: work.timers().map(|deadline| deadline.saturating_sub(now)).collect(),
+            fds: work.fds().collect(),
+        }
+    }
+
+    pub(crate) fn schedule_timer(&self, deadline: Duration) -> Timer {
         self.work.lock().schedule_timer(deadline)
     }
 
-    pub(crate) fn schedule_io(&self, fd: i32) -> Notified {
+    fn cancel_timer(&self, key: (Duration, u64)) {
+        self.work.lock().cancel_timer(key)
+    }
+
+    pub(crate) fn schedule_io(&self, fd: i32) -> IoReady {
         self.work.lock().schedule_io(fd)
     }
+
+    fn cancel_io(&self, key: (i32, u64)) {
+        self.work.lock().cancel_io(key)
+    }
 }
 
+/// An owned permission to join on a task, by awaiting it.
+///
+/// Dropping the handle detaches the task, which keeps running. Polling the
+/// handle again after it resolved panics.
+///
+/// The handle can only be sent to another thread if the output of the task
+/// can:
+///
+/// ```compile_fail
+/// fn assert_send<T: Send>(_: T) {}
+/// let handle = hl_guest_async::spawn_local(async { std::rc::Rc::new(1) });
+/// assert_send(handle);
+/// ```
 pub struct JoinHandle<T> {
-    result: BoxFuture<'static, Option<T>>,
-    abort: Notify,
+    // the task itself, there's no channel between it and the handle
+    task: Arc<dyn Join<T>>,
+    // the handle takes the output of the task, so it can only be sent to
+    // another thread if the output can, even for a task of `spawn_local`
+    _output: PhantomData<T>,
 }
 
-impl JoinHandle<()> {
-    pub fn abort(self) {
-        self.abort.notify_waiters();
+// the output is never pinned
+impl<T> Unpin for JoinHandle<T> {}
+
+impl<T> JoinHandle<T> {
+    /// Cancels the task. Awaiting the handle afterwards returns
+    /// [`JoinError::Cancelled`], unless the task already completed.
+    pub fn abort(&self) {
+        self.abort_handle().abort();
+    }
+
+    /// Returns a handle that can cancel the task, without joining it.
+    pub fn abort_handle(&self) -> AbortHandle {
+        AbortHandle(self.task.clone().runnable())
     }
+
+    /// Whether the task completed or was cancelled.
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+
+    /// Lets the task run in the background, without a way to join it.
+    pub fn detach(self) {}
 }
 
 impl<T> Future for JoinHandle<T> {
-    type Output = Option<T>;
+    type Output = Result<T, JoinError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.task.poll_join(cx)
+    }
+}
+
+/// Why a task didn't complete.
+///
+/// Tasks can only be cancelled: a panic aborts the whole guest, so it's never
+/// reported as a join error.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum JoinError {
+    /// The task was aborted.
+    Cancelled,
+}
+
+impl JoinError {
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self, JoinError::Cancelled)
+    }
+}
+
+impl fmt::Display for JoinError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JoinError::Cancelled => f.write_str("task was cancelled"),
+        }
+    }
+}
+
+impl core::error::Error for JoinError {}
+
+/// A handle to cancel a task, returned by [`JoinHandle::abort_handle`].
+#[derive(Clone)]
+pub struct AbortHandle(Arc<dyn Runnable>);
 
-    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        self.result.as_mut().poll(cx)
+impl AbortHandle {
+    /// Cancels the task.
+    pub fn abort(&self) {
+        self.0.clone().abort();
     }
 }
@@ -1,83 +1,324 @@
-use alloc::sync::Arc;
+use alloc::sync::{Arc, Weak};
 use alloc::task::Wake;
+use alloc::vec::Vec;
+use core::cell::UnsafeCell;
 use core::future::Future;
-use core::task::Context;
-use core::task::Poll;
-use futures::future::LocalBoxFuture;
-use futures::future::{BoxFuture, FutureExt};
+use core::mem;
+use core::panic::Location;
+use core::pin::Pin;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::task::{Context, Poll, Waker};
 use spin::Mutex;
 
-use crate::channel::Sender;
+use super::{JoinError, Runtime};
+use crate::coop;
+use crate::debug::TaskSnapshot;
 
-pub struct SpawnTask {
-    future: Mutex<BoxFuture<'static, ()>>,
-    executor: Sender<Task>,
+// A task that can be scheduled in the ready queue of the runtime.
+pub(crate) trait Runnable: Send + Sync {
+    // Polls the task once, if it's not finished yet.
+    fn run(self: Arc<Self>);
+
+    // Cancels the task, which completes the next time it runs.
+    fn abort(self: Arc<Self>);
+
+    fn snapshot(&self) -> TaskSnapshot;
 }
 
-pub struct BlockOnTask {
-    executor: Sender<Task>,
+// The type-erased view of a task that its `JoinHandle` holds.
+pub(crate) trait Join<T>: Send + Sync {
+    fn poll_join(&self, cx: &mut Context<'_>) -> Poll<Result<T, JoinError>>;
+
+    fn is_finished(&self) -> bool;
+
+    fn runnable(self: Arc<Self>) -> Arc<dyn Runnable>;
 }
 
-pub enum Task {
-    Spawn(Arc<SpawnTask>),
-    BlockOn(Arc<BlockOnTask>),
+// Everything the runtime needs to schedule a task, stored in the same
+// allocation as its future.
+struct Header {
+    id: u64,
+    spawned_at: &'static Location<'static>,
+    // the slot of the task in the runtime's `Tasks`
+    slot: usize,
+    // whether the task is in the ready queue, so that it's never in it twice
+    queued: AtomicBool,
+    aborted: AtomicBool,
+    finished: AtomicBool,
+    // the task awaiting the `JoinHandle`
+    join_waker: Mutex<Option<Waker>>,
 }
 
-impl Wake for SpawnTask {
-    fn wake(self: Arc<Self>) {
-        self.executor.send(Task::Spawn(self.clone()));
+enum Stage<F: Future> {
+    Running(F),
+    Finished(Result<F::Output, JoinError>),
+    // the result was taken by the `JoinHandle`
+    Joined,
+}
+
+// A spawned task: its header, its future, and then its result, in a single
+// allocation.
+pub(crate) struct Task<F: Future> {
+    header: Header,
+    stage: UnsafeCell<Stage<F>>,
+}
+
+// The stage is only accessed by the runtime until the task finishes, and then
+// by the `JoinHandle`, so sharing a task moves its future and its output
+// between threads, but never touches them from two threads at once.
+unsafe impl<F: Future + Send> Sync for Task<F> where F::Output: Send {}
+
+/// The future of a task spawned with `spawn_local`, and then its output.
+///
+/// It's what lets tasks that are not `Send` share the harness of the others,
+/// by assuming that the guest runs on a single thread: the runtime polls the
+/// tasks from whichever thread drives it, so the future would otherwise be
+/// polled or dropped from another thread than the one that spawned it.
+pub(crate) struct Local<T>(T);
+
+unsafe impl<T> Send for Local<T> {}
+
+impl<T> Local<T> {
+    pub(crate) fn new(value: T) -> Self {
+        Local(value)
     }
 }
 
-impl Wake for BlockOnTask {
+impl<F: Future> Future for Local<F> {
+    type Output = Local<F::Output>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // The future is never moved out of the wrapper
+        let future = unsafe { self.map_unchecked_mut(|local| &mut local.0) };
+        future.poll(cx).map(Local)
+    }
+}
+
+// The output of a task's future, turned into the result of its `JoinHandle`:
+// either as is, or unwrapped from `Local`.
+pub(crate) trait IntoOutput<T> {
+    fn into_output(self) -> T;
+}
+
+impl<T> IntoOutput<T> for T {
+    fn into_output(self) -> T {
+        self
+    }
+}
+
+impl<T> IntoOutput<T> for Local<T> {
+    fn into_output(self) -> T {
+        self.0
+    }
+}
+
+impl<F: Future + Send + 'static> Task<F>
+where
+    F::Output: Send,
+{
+    // Allocates a task for the given future, registers it in the runtime and
+    // schedules it.
+    pub(crate) fn spawn(future: F, spawned_at: &'static Location<'static>) -> Arc<Self> {
+        let task = Arc::new_cyclic(|task: &Weak<Self>| {
+            let (id, slot) = Runtime::global().register(task.clone());
+            Task {
+                header: Header {
+                    id,
+                    spawned_at,
+                    slot,
+                    queued: AtomicBool::new(false),
+                    aborted: AtomicBool::new(false),
+                    finished: AtomicBool::new(false),
+                    join_waker: Mutex::new(None),
+                },
+                stage: UnsafeCell::new(Stage::Running(future)),
+            }
+        });
+        task.schedule();
+        task
+    }
+
+    fn schedule(self: &Arc<Self>) {
+        if self.header.finished.load(Ordering::SeqCst) {
+            return;
+        }
+        if !self.header.queued.swap(true, Ordering::SeqCst) {
+            Runtime::global().schedule(self.clone());
+        }
+    }
+
+    fn complete(&self, stage: &mut Stage<F>, result: Result<F::Output, JoinError>) {
+        // drops the future
+        *stage = Stage::Finished(result);
+        self.header.finished.store(true, Ordering::SeqCst);
+        Runtime::global().unregister(self.header.slot);
+        if let Some(waker) = self.header.join_waker.lock().take() {
+            waker.wake();
+        }
+    }
+}
+
+impl<F: Future> Drop for Task<F> {
+    fn drop(&mut self) {
+        // nothing can wake the task anymore
+        if !self.header.finished.load(Ordering::SeqCst) {
+            Runtime::global().unregister(self.header.slot);
+        }
+    }
+}
+
+impl<F: Future + Send + 'static> Wake for Task<F>
+where
+    F::Output: Send,
+{
     fn wake(self: Arc<Self>) {
-        self.executor.send(Task::BlockOn(self.clone()));
+        self.schedule();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.schedule();
     }
 }
 
-impl SpawnTask {
-    pub fn poll(self: Arc<Self>) {
-        // Create a waker from the `Task` instance. This
-        // uses the `Wake` impl from above.
-        let waker = self.clone().into();
+impl<F: Future + Send + 'static> Runnable for Task<F>
+where
+    F::Output: Send,
+{
+    fn run(self: Arc<Self>) {
+        // wakes from now on schedule the task again
+        self.header.queued.store(false, Ordering::SeqCst);
+
+        // The stage is only accessed from the runtime's thread, and never
+        // while the future is being polled by someone else
+        let stage = unsafe { &mut *self.stage.get() };
+        let Stage::Running(future) = stage else {
+            return;
+        };
+
+        if self.header.aborted.load(Ordering::SeqCst) {
+            self.complete(stage, Err(JoinError::Cancelled));
+            return;
+        }
+
+        // The future lives in the task's allocation, and is never moved out
+        // of it: it's only dropped in place when the task completes
+        let future = unsafe { Pin::new_unchecked(future) };
+        let waker = Waker::from(self.clone());
         let mut cx = Context::from_waker(&waker);
 
-        // No other thread ever tries to lock the future
-        let mut future = self.future.try_lock().unwrap();
+        // Poll the inner future with a fresh budget
+        if let Poll::Ready(output) = coop::with_budget(|| future.poll(&mut cx)) {
+            self.complete(stage, Ok(output));
+        }
+    }
+
+    fn abort(self: Arc<Self>) {
+        self.header.aborted.store(true, Ordering::SeqCst);
+        self.schedule();
+    }
+
+    fn snapshot(&self) -> TaskSnapshot {
+        TaskSnapshot {
+            id: self.header.id,
+            spawned_at: self.header.spawned_at,
+            ready: self.header.queued.load(Ordering::SeqCst),
+        }
+    }
+}
+
+impl<F: Future + Send + 'static, T> Join<T> for Task<F>
+where
+    F::Output: Send + IntoOutput<T>,
+{
+    fn poll_join(&self, cx: &mut Context<'_>) -> Poll<Result<T, JoinError>> {
+        if !self.header.finished.load(Ordering::SeqCst) {
+            *self.header.join_waker.lock() = Some(cx.waker().clone());
+            return Poll::Pending;
+        }
+
+        // The task is finished, so the runtime won't touch the stage anymore
+        let stage = unsafe { &mut *self.stage.get() };
+        match mem::replace(stage, Stage::Joined) {
+            Stage::Finished(result) => Poll::Ready(result.map(IntoOutput::into_output)),
+            _ => panic!("`JoinHandle` polled after completion"),
+        }
+    }
 
-        // Poll the inner future
-        let _ = future.as_mut().poll(&mut cx);
+    fn is_finished(&self) -> bool {
+        self.header.finished.load(Ordering::SeqCst)
     }
+
+    fn runnable(self: Arc<Self>) -> Arc<dyn Runnable> {
+        self
+    }
+}
+
+// The tasks that didn't complete, for introspection. The slots of completed
+// tasks are reused, so registering a task doesn't allocate once there have
+// been as many slots as tasks.
+#[derive(Default)]
+pub(crate) struct Tasks {
+    slots: Vec<Option<Weak<dyn Runnable>>>,
+    free: Vec<usize>,
+}
+
+impl Tasks {
+    pub(crate) fn insert(&mut self, task: Weak<dyn Runnable>) -> usize {
+        match self.free.pop() {
+            Some(slot) => {
+                self.slots[slot] = Some(task);
+                slot
+            }
+            None => {
+                self.slots.push(Some(task));
+                self.slots.len() - 1
+            }
+        }
+    }
+
+    pub(crate) fn remove(&mut self, slot: usize) {
+        self.slots[slot] = None;
+        self.free.push(slot);
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.slots.len() - self.free.len()
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = Arc<dyn Runnable>> + '_ {
+        self.slots.iter().flatten().filter_map(Weak::upgrade)
+    }
+}
+
+// Wakes the future passed to `block_on`, which lives on the stack rather than
+// in the ready queue.
+pub(crate) struct BlockOnTask {
+    woken: AtomicBool,
 }
 
 impl BlockOnTask {
-    pub fn poll<T>(self: Arc<Self>, future: &mut LocalBoxFuture<'_, T>) -> Poll<T> {
-        // Create a waker from the `Task` instance. This
-        // uses the `Wake` impl from above.
-        let waker = self.clone().into();
-        let mut cx = Context::from_waker(&waker);
+    pub(crate) fn new() -> Self {
+        // polled right away
+        BlockOnTask {
+            woken: AtomicBool::new(true),
+        }
+    }
 
-        // Poll the inner future
-        future.as_mut().poll(&mut cx)
+    pub(crate) fn take_woken(&self) -> bool {
+        self.woken.swap(false, Ordering::SeqCst)
+    }
+
+    pub(crate) fn is_woken(&self) -> bool {
+        self.woken.load(Ordering::SeqCst)
     }
 }
 
-impl Task {
-    // Spawns a new task with the given future.
-    //
-    // Initializes a new Task harness containing the given future and pushes it
-    // onto `sender`. The receiver half of the channel will get the task and
-    // execute it.
-    pub fn spawn(future: impl Future<Output = ()> + Send + 'static, sender: &Sender<Task>) {
-        let task = Task::Spawn(Arc::new(SpawnTask {
-            future: Mutex::new(future.fuse().boxed()),
-            executor: sender.clone(),
-        }));
-        sender.send(task);
+impl Wake for BlockOnTask {
+    fn wake(self: Arc<Self>) {
+        self.woken.store(true, Ordering::SeqCst);
     }
 
-    pub fn block_on(sender: &Sender<Task>) {
-        let task = Arc::new(BlockOnTask { executor: sender.clone() });
-        sender.send(Task::BlockOn(task.clone()));
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.woken.store(true, Ordering::SeqCst);
     }
 }
@@ -1,58 +1,102 @@
 
-use alloc::collections::{BTreeMap, BinaryHeap};
+use alloc::collections::BTreeMap;
 use alloc::vec::Vec;
-use core::{cmp::Reverse, ops::Deref, time::Duration};
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
 
 use crate::{
-    host::{get_time, poll_read, sleep},
+    events::{self, EVENTS_FD},
+    host::{get_time, poll_read},
     notify::{Notified, Notify},
 };
 
-struct Unordered<T>(pub T);
+use super::Runtime;
 
-impl<T> Ord for Unordered<T> {
-    fn cmp(&self, _: &Self) -> core::cmp::Ordering {
-        core::cmp::Ordering::Equal
-    }
+// Timers are ordered by deadline, and then by the order they were scheduled in
+type TimerKey = (Duration, u64);
+
+/// A timer scheduled in the runtime, that completes when its deadline is
+/// reached.
+///
+/// Dropping it removes it from the runtime, so that it doesn't keep the
+/// runtime waiting for a deadline nobody cares about anymore.
+pub(crate) struct Timer {
+    key: TimerKey,
+    notified: Notified,
 }
-impl<T> PartialOrd for Unordered<T> {
-    fn partial_cmp(&self, _: &Self) -> Option<core::cmp::Ordering> {
-        Some(core::cmp::Ordering::Equal)
+
+impl Future for Timer {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        Pin::new(&mut self.notified).poll(cx)
     }
 }
-impl<T> PartialEq for Unordered<T> {
-    fn eq(&self, _: &Self) -> bool {
-        true
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        Runtime::global().cancel_timer(self.key);
     }
 }
-impl<T> Eq for Unordered<T> {}
 
-impl<T> Deref for Unordered<T> {
-    type Target = T;
+// A file descriptor, and the id of the registration waiting on it
+type IoKey = (i32, u64);
 
-    fn deref(&self) -> &Self::Target {
-        &self.0
+/// A wait for a file descriptor to be ready, that completes when it is.
+///
+/// Dropping it deregisters it once nobody else waits on the same file
+/// descriptor, so that the runtime doesn't keep waiting for input nobody
+/// cares about anymore.
+pub(crate) struct IoReady {
+    key: IoKey,
+    notified: Notified,
+}
+
+impl Future for IoReady {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        Pin::new(&mut self.notified).poll(cx)
     }
 }
 
+impl Drop for IoReady {
+    fn drop(&mut self) {
+        Runtime::global().cancel_io(self.key);
+    }
+}
+
+// The waits on a file descriptor, removed once it's ready or once all of them
+// were dropped
+struct IoWaiters {
+    id: u64,
+    notify: Notify,
+    count: usize,
+}
+
 #[derive(Default)]
 pub(super) struct RuntimeWork {
-    timers: BinaryHeap<(Reverse<Duration>, Unordered<Notify>)>,
-    ios: BTreeMap<i32, Notify>,
+    timers: BTreeMap<TimerKey, Notify>,
+    next_timer_id: u64,
+    ios: BTreeMap<i32, IoWaiters>,
+    next_io_id: u64,
 }
 
 impl RuntimeWork {
-    pub(super) fn work(&mut self) {
+    /// Waits for the next timer, input or event, for up to `max_wait`.
+    pub(super) fn work(&mut self, max_wait: Option<Duration>) {
         let mut timeout = None;
         let mut now = None;
-        while let Some((Reverse(deadline), notify)) = self.timers.peek() {
+        while let Some(timer) = self.timers.first_entry() {
             // we have a scheduled timer
             let now = *now.get_or_insert_with(|| get_time());
+            let (deadline, _) = timer.key();
             if *deadline <= now {
                 // and the timer needed to wake up
                 timeout = Some(Duration::ZERO);
-                notify.notify_waiters();
-                self.timers.pop();
+                timer.remove().notify_waiters();
             } else {
                 // the timer doesn't need to wake up yet
                 // since the times are sorted by deadline,
@@ -62,44 +106,100 @@ impl RuntimeWork {
             }
         }
 
+        if let Some(max_wait) = max_wait {
+            timeout = Some(timeout.map_or(max_wait, |timeout: Duration| timeout.min(max_wait)));
+        }
+
         if timeout == Some(Duration::ZERO) {
             // we need to wake up immediately as at least one
             // timer is ready to wake up
             return;
         }
 
-        // we would normally have only one io to wake, as we only have
-        // one IO channel (stdin). If we have more, then the program has
-        // some race condition. This will change if we have more IO
-        // channels
         let mut fds: Vec<_> = self.ios.keys().copied().collect();
-        if !fds.is_empty() {
-            // we have IO work to do
-            // wait for IO to be ready, or until a timer timeout
-            if poll_read(&mut fds, timeout).is_ok() {
-                for fd in fds {
-                    if fd >= 0 {
-                        if let Some(notify) = self.ios.remove(&fd) {
-                            notify.notify_waiters();
-                        }
-                    }
+        if fds.is_empty() && timeout.is_none() {
+            // nothing to wait for
+            return;
+        }
+        // the events are always waited on, so that the host can cancel
+        // `CancellationToken::host` even if no task waits for events
+        if !self.ios.contains_key(&EVENTS_FD) {
+            fds.push(EVENTS_FD);
+        }
+        // wait for IO to be ready, or until a timer timeout
+        if poll_read(&mut fds, timeout).is_err() {
+            return;
+        }
+        let mut has_events = false;
+        for fd in fds {
+            has_events |= fd == EVENTS_FD;
+            if fd >= 0 {
+                if let Some(waiters) = self.ios.remove(&fd) {
+                    waiters.notify.notify_waiters();
                 }
             }
-        } else if let Some(timeout) = timeout {
-            // no IO work to do, just wait for the timer
-            sleep(Some(timeout));
+        }
+        if has_events {
+            events::dispatch();
         }
     }
 
-    pub(crate) fn schedule_timer(&mut self, deadline: Duration) -> Notified {
+    /// Whether there are timers or inputs to wait for.
+    pub(super) fn is_waiting(&self) -> bool {
+        !self.timers.is_empty() || !self.ios.is_empty()
+    }
+
+    /// The deadlines of the scheduled timers, in order.
+    pub(super) fn timers(&self) -> impl Iterator<Item = Duration> + '_ {
+        self.timers.keys().map(|(deadline, _)| *deadline)
+    }
+
+    /// The file descriptors waited on.
+    pub(super) fn fds(&self) -> impl Iterator<Item = i32> + '_ {
+        self.ios.keys().copied()
+    }
+
+    pub(crate) fn schedule_timer(&mut self, deadline: Duration) -> Timer {
+        let key = (deadline, self.next_timer_id);
+        self.next_timer_id += 1;
         let notify = Notify::new();
         let notified = notify.notified();
-        self.timers.push((Reverse(deadline), Unordered(notify)));
-        notified
+        self.timers.insert(key, notify);
+        Timer { key, notified }
+    }
+
+    pub(crate) fn cancel_timer(&mut self, key: TimerKey) {
+        self.timers.remove(&key);
+    }
+
+    pub(crate) fn schedule_io(&mut self, fd: i32) -> IoReady {
+        let waiters = self.ios.entry(fd).or_insert_with(|| {
+            let id = self.next_io_id;
+            self.next_io_id += 1;
+            IoWaiters {
+                id,
+                notify: Notify::new(),
+                count: 0,
+            }
+        });
+        waiters.count += 1;
+        IoReady {
+            key: (fd, waiters.id),
+            notified: waiters.notify.notified(),
+        }
     }
 
-    pub(crate) fn schedule_io(&mut self, fd: i32) -> Notified {
-        let notify = self.ios.entry(fd).or_insert_with(|| Notify::new());
-        notify.notified()
+    pub(crate) fn cancel_io(&mut self, (fd, id): IoKey) {
+        // the registration is already gone if the fd was ready since
+        let Some(waiters) = self.ios.get_mut(&fd) else {
+            return;
+        };
+        if waiters.id != id {
+            return;
+        }
+        waiters.count -= 1;
+        if waiters.count == 0 {
+            self.ios.remove(&fd);
+        }
     }
 }
@@ -0,0 +1,371 @@
+//! Asynchronous sequences of values, and combinators to build pipelines out
+//! of them.
+//!
+//! Channel receivers, [`Interval`](crate::time::Interval)s, event
+//! [`Subscription`](crate::events::Subscription)s and
+//! [`Stdin::lines`](crate::io::Stdin::lines) are streams.
+
+use alloc::vec::Vec;
+use core::fmt;
+use core::future::Future;
+use core::mem;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+
+pub use futures::stream::{empty, iter, once, pending, poll_fn, Stream};
+pub use futures::stream::{Collect, Fuse, Map, Next, Take};
+
+use crate::time::{sleep, sleep_until, Instant, Sleep};
+
+/// Combinators for [`Stream`]s.
+pub trait StreamExt: Stream {
+    /// Waits for the next item, or `None` once the stream ended.
+    fn next(&mut self) -> Next<'_, Self>
+    where
+        Self: Unpin,
+    {
+        futures::StreamExt::next(self)
+    }
+
+    /// Maps each item with `f`.
+    fn map<T, F>(self, f: F) -> Map<Self, F>
+    where
+        F: FnMut(Self::Item) -> T,
+        Self: Sized,
+    {
+        futures::StreamExt::map(self, f)
+    }
+
+    /// Yields only the items for which `predicate` returns true.
+    fn filter<F>(self, predicate: F) -> Filter<Self, F>
+    where
+        F: FnMut(&Self::Item) -> bool,
+        Self: Sized,
+    {
+        Filter {
+            stream: self,
+            predicate,
+        }
+    }
+
+    /// Maps each item with `f`, and yields only the `Some` results.
+    fn filter_map<T, F>(self, f: F) -> FilterMap<Self, F>
+    where
+        F: FnMut(Self::Item) -> Option<T>,
+        Self: Sized,
+    {
+        FilterMap { stream: self, f }
+    }
+
+    /// Ends the stream after `n` items.
+    fn take(self, n: usize) -> Take<Self>
+    where
+        Self: Sized,
+    {
+        futures::StreamExt::take(self, n)
+    }
+
+    /// Makes the stream keep returning `None` once it ended.
+    fn fuse(self) -> Fuse<Self>
+    where
+        Self: Sized,
+    {
+        futures::StreamExt::fuse(self)
+    }
+
+    /// Collects all the items, once the stream ended.
+    fn collect<C: Default + Extend<Self::Item>>(self) -> Collect<Self, C>
+    where
+        Self: Sized,
+    {
+        futures::StreamExt::collect(self)
+    }
+
+    /// Yields the items of both streams as they come, until both ended.
+    ///
+    /// When both have items ready, they take turns.
+    fn merge<S>(self, other: S) -> Merge<Self, S>
+    where
+        S: Stream<Item = Self::Item>,
+        Self: Sized,
+    {
+        Merge {
+            a: Some(self),
+            b: Some(other),
+            b_first: false,
+        }
+    }
+
+    /// Yields at most one item every `duration`, delaying the items that
+    /// come sooner.
+    fn throttle(self, duration: Duration) -> Throttle<Self>
+    where
+        Self: Sized,
+    {
+        Throttle {
+            stream: self,
+            duration,
+            sleep: sleep_until(Instant::now()),
+        }
+    }
+
+    /// Yields `Err(Elapsed)` every time no item comes within `duration`,
+    /// and keeps waiting for the next one.
+    fn timeout(self, duration: Duration) -> Timeout<Self>
+    where
+        Self: Sized,
+    {
+        Timeout {
+            stream: self,
+            duration,
+            sleep: None,
+        }
+    }
+
+    /// Groups the items in chunks of up to `max_size` items, yielding a
+    /// chunk once it's full, or `duration` after its first item came.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_size` is zero.
+    fn chunks_timeout(self, max_size: usize, duration: Duration) -> ChunksTimeout<Self>
+    where
+        Self: Sized,
+    {
+        assert!(max_size > 0, "`max_size` must be non-zero");
+        ChunksTimeout {
+            stream: Some(self),
+            max_size,
+            duration,
+            items: Vec::new(),
+            sleep: None,
+        }
+    }
+}
+
+impl<S: Stream + ?Sized> StreamExt for S {}
+
+/// Stream returned by [`StreamExt::filter`].
+pub struct Filter<S, F> {
+    stream: S,
+    predicate: F,
+}
+
+impl<S: Stream, F: FnMut(&S::Item) -> bool> Stream for Filter<S, F> {
+    type Item = S::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<S::Item>> {
+        // `stream` is the only field that is structurally pinned
+        let this = unsafe { self.get_unchecked_mut() };
+        let mut stream = unsafe { Pin::new_unchecked(&mut this.stream) };
+        loop {
+            match stream.as_mut().poll_next(cx) {
+                Poll::Ready(Some(item)) if !(this.predicate)(&item) => {}
+                poll => return poll,
+            }
+        }
+    }
+}
+
+/// Stream returned by [`StreamExt::filter_map`].
+pub struct FilterMap<S, F> {
+    stream: S,
+    f: F,
+}
+
+impl<S: Stream, T, F: FnMut(S::Item) -> Option<T>> Stream for FilterMap<S, F> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        // `stream` is the only field that is structurally pinned
+        let this = unsafe { self.get_unchecked_mut() };
+        let mut stream = unsafe { Pin::new_unchecked(&mut this.stream) };
+        loop {
+            match stream.as_mut().poll_next(cx) {
+                Poll::Ready(Some(item)) => {
+                    if let Some(item) = (this.f)(item) {
+                        return Poll::Ready(Some(item));
+                    }
+                }
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+/// Stream returned by [`StreamExt::merge`].
+pub struct Merge<A, B> {
+    // `None` once they ended
+    a: Option<A>,
+    b: Option<B>,
+    // polls `b` before `a` next time, for fairness
+    b_first: bool,
+}
+
+// Polls `stream` if it didn't end yet
+fn poll_next_in<S: Stream>(
+    mut stream: Pin<&mut Option<S>>,
+    cx: &mut Context<'_>,
+) -> Poll<Option<S::Item>> {
+    let Some(inner) = stream.as_mut().as_pin_mut() else {
+        return Poll::Ready(None);
+    };
+    let poll = inner.poll_next(cx);
+    if let Poll::Ready(None) = poll {
+        stream.set(None);
+    }
+    poll
+}
+
+// Polls `first`, and `second` if `first` has no item ready
+fn poll_next_either<A: Stream, B: Stream<Item = A::Item>>(
+    first: Pin<&mut Option<A>>,
+    second: Pin<&mut Option<B>>,
+    cx: &mut Context<'_>,
+) -> Poll<Option<A::Item>> {
+    let first = match poll_next_in(first, cx) {
+        Poll::Ready(Some(item)) => return Poll::Ready(Some(item)),
+        poll => poll,
+    };
+    match poll_next_in(second, cx) {
+        Poll::Ready(None) if first.is_pending() => Poll::Pending,
+        poll => poll,
+    }
+}
+
+impl<A: Stream, B: Stream<Item = A::Item>> Stream for Merge<A, B> {
+    type Item = A::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<A::Item>> {
+        // `a` and `b` are structurally pinned
+        let this = unsafe { self.get_unchecked_mut() };
+        let a = unsafe { Pin::new_unchecked(&mut this.a) };
+        let b = unsafe { Pin::new_unchecked(&mut this.b) };
+        let b_first = this.b_first;
+        this.b_first = !b_first;
+        if b_first {
+            poll_next_either(b, a, cx)
+        } else {
+            poll_next_either(a, b, cx)
+        }
+    }
+}
+
+/// Stream returned by [`StreamExt::throttle`].
+pub struct Throttle<S> {
+    stream: S,
+    duration: Duration,
+    // elapses when the next item can be yielded
+    sleep: Sleep,
+}
+
+impl<S: Stream> Stream for Throttle<S> {
+    type Item = S::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<S::Item>> {
+        // `stream` is the only field that is structurally pinned
+        let this = unsafe { self.get_unchecked_mut() };
+        let stream = unsafe { Pin::new_unchecked(&mut this.stream) };
+        if Pin::new(&mut this.sleep).poll(cx).is_pending() {
+            return Poll::Pending;
+        }
+        let poll = stream.poll_next(cx);
+        if let Poll::Ready(Some(_)) = poll {
+            this.sleep.reset(Instant::now() + this.duration);
+        }
+        poll
+    }
+}
+
+/// Stream returned by [`StreamExt::timeout`].
+pub struct Timeout<S> {
+    stream: S,
+    duration: Duration,
+    // elapses when the item being waited on is late, set on the first poll
+    sleep: Option<Sleep>,
+}
+
+impl<S: Stream> Stream for Timeout<S> {
+    type Item = Result<S::Item, Elapsed>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        // `stream` is the only field that is structurally pinned
+        let this = unsafe { self.get_unchecked_mut() };
+        let stream = unsafe { Pin::new_unchecked(&mut this.stream) };
+        if let Poll::Ready(item) = stream.poll_next(cx) {
+            this.sleep = None;
+            return Poll::Ready(item.map(Ok));
+        }
+        let duration = this.duration;
+        let sleep = this.sleep.get_or_insert_with(|| sleep(duration));
+        if Pin::new(&mut *sleep).poll(cx).is_pending() {
+            return Poll::Pending;
+        }
+        this.sleep = None;
+        Poll::Ready(Some(Err(Elapsed)))
+    }
+}
+
+/// Yielded by [`StreamExt::timeout`] when an item is late.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Elapsed;
+
+impl fmt::Display for Elapsed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("no item within the timeout")
+    }
+}
+
+impl core::error::Error for Elapsed {}
+
+/// Stream returned by [`StreamExt::chunks_timeout`].
+pub struct ChunksTimeout<S: Stream> {
+    // `None` once it ended
+    stream: Option<S>,
+    max_size: usize,
+    duration: Duration,
+    // the chunk being filled
+    items: Vec<S::Item>,
+    // elapses when the chunk must be yielded, set on its first item
+    sleep: Option<Sleep>,
+}
+
+impl<S: Stream> Stream for ChunksTimeout<S> {
+    type Item = Vec<S::Item>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Vec<S::Item>>> {
+        // `stream` is the only field that is structurally pinned
+        let this = unsafe { self.get_unchecked_mut() };
+        let mut stream = unsafe { Pin::new_unchecked(&mut this.stream) };
+        loop {
+            match poll_next_in(stream.as_mut(), cx) {
+                Poll::Ready(Some(item)) => {
+                    if this.items.is_empty() {
+                        this.items.reserve_exact(this.max_size);
+                        this.sleep = Some(sleep(this.duration));
+                    }
+                    this.items.push(item);
+                    if this.items.len() >= this.max_size {
+                        break;
+                    }
+                }
+                Poll::Ready(None) if this.items.is_empty() => return Poll::Ready(None),
+                Poll::Ready(None) => break,
+                Poll::Pending => {
+                    let elapsed = this
+                        .sleep
+                        .as_mut()
+                        .is_some_and(|sleep| Pin::new(sleep).poll(cx).is_ready());
+                    if !elapsed {
+                        return Poll::Pending;
+                    }
+                    break;
+                }
+            }
+        }
+        this.sleep = None;
+        Poll::Ready(Some(mem::take(&mut this.items)))
+    }
+}
@@ -0,0 +1,72 @@
+use core::future::poll_fn;
+use spin::Mutex;
+
+use crate::notify::{Notify, Waiter};
+
+/// Makes a number of tasks wait until all of them reach the same point.
+///
+/// The barrier can be reused once all the tasks passed it.
+pub struct Barrier {
+    n: usize,
+    state: Mutex<State>,
+    // notified when all the tasks reached the barrier
+    notify: Notify,
+}
+
+struct State {
+    arrived: usize,
+    // incremented every time all the tasks reach the barrier
+    generation: u64,
+}
+
+/// Returned by [`Barrier::wait`].
+#[derive(Clone, Copy, Debug)]
+pub struct BarrierWaitResult(bool);
+
+impl BarrierWaitResult {
+    /// Whether this task was the last one to reach the barrier. Exactly one
+    /// task is the leader every time the barrier is passed.
+    pub fn is_leader(&self) -> bool {
+        self.0
+    }
+}
+
+impl Barrier {
+    /// Creates a barrier for `n` tasks. A barrier for zero tasks behaves like
+    /// a barrier for one.
+    pub fn new(n: usize) -> Self {
+        Barrier {
+            n: n.max(1),
+            state: Mutex::new(State {
+                arrived: 0,
+                generation: 0,
+            }),
+            notify: Notify::new(),
+        }
+    }
+
+    /// Waits until `n` tasks called `wait`.
+    pub async fn wait(&self) -> BarrierWaitResult {
+        let generation = {
+            let mut state = self.state.lock();
+            state.arrived += 1;
+            if state.arrived == self.n {
+                state.arrived = 0;
+                state.generation += 1;
+                drop(state);
+                self.notify.notify_waiters();
+                return BarrierWaitResult(true);
+            }
+            state.generation
+        };
+
+        let mut waiter = Waiter::default();
+        poll_fn(|cx| {
+            waiter.poll_until(&self.notify, cx, || {
+                (self.state.lock().generation != generation).then_some(())
+            })
+        })
+        .await;
+        BarrierWaitResult(false)
+    }
+}
@@ -0,0 +1,187 @@
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use core::fmt;
+use core::future::{poll_fn, Future};
+use core::mem;
+use core::pin::{pin, Pin};
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::task::Poll;
+use spin::Mutex;
+
+use crate::events::{self, EVENTS_FD};
+use crate::notify::{Notify, Waiter};
+use crate::runtime::{IoReady, Runtime};
+
+/// A token to ask tasks to stop, e.g., to shut down gracefully.
+///
+/// Clones share the same state. Cancelling a token also cancels its
+/// children, created with [`child_token`](CancellationToken::child_token),
+/// but cancelling a child doesn't cancel its parent.
+#[derive(Clone)]
+pub struct CancellationToken {
+    node: Arc<Node>,
+}
+
+struct Node {
+    cancelled: AtomicBool,
+    // whether the host can cancel the token, through its ancestors
+    host: bool,
+    // notified when the token is cancelled
+    notify: Notify,
+    children: Mutex<Vec<Weak<Node>>>,
+}
+
+impl Node {
+    fn new(host: bool) -> Self {
+        Node {
+            cancelled: AtomicBool::new(false),
+            host,
+            notify: Notify::new(),
+            children: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn cancel(&self) {
+        if self.cancelled.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        self.notify.notify_waiters();
+        let children = mem::take(&mut *self.children.lock());
+        for child in children {
+            if let Some(child) = child.upgrade() {
+                child.cancel();
+            }
+        }
+    }
+}
+
+// The token of the host, created on first use, and replaced once cancelled
+// when the host calls the guest again
+static HOST: Mutex<Option<CancellationToken>> = Mutex::new(None);
+
+fn host_token() -> CancellationToken {
+    HOST.lock()
+        .get_or_insert_with(|| CancellationToken {
+            node: Arc::new(Node::new(true)),
+        })
+        .clone()
+}
+
+/// Cancels the token of the host, when it sent the cancel event.
+pub(crate) fn cancel_host() {
+    host_token().cancel();
+}
+
+/// Replaces the token of the host if it was cancelled, so that a new call
+/// from the host doesn't start cancelled.
+pub(crate) fn reset_host() {
+    let mut host = HOST.lock();
+    if host
+        .as_ref()
+        .is_some_and(|token| token.node.cancelled.load(Ordering::SeqCst))
+    {
+        *host = None;
+    }
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        CancellationToken {
+            node: Arc::new(Node::new(false)),
+        }
+    }
+
+    /// Returns the token the host cancels to ask the guest call in progress
+    /// to stop, before terminating it.
+    ///
+    /// Once the host cancelled it, the guest is expected to return as soon
+    /// as possible. The next call from the host gets a new token, while the
+    /// tokens obtained before stay cancelled.
+    pub fn host() -> Self {
+        host_token()
+    }
+
+    /// Creates a token that is cancelled when this one is, or when it's
+    /// cancelled itself.
+    pub fn child_token(&self) -> Self {
+        let child = Arc::new(Node::new(self.node.host));
+        let mut children = self.node.children.lock();
+        if self.node.cancelled.load(Ordering::SeqCst) {
+            child.cancelled.store(true, Ordering::SeqCst);
+        } else {
+            children.retain(|child| child.strong_count() > 0);
+            children.push(Arc::downgrade(&child));
+        }
+        CancellationToken { node: child }
+    }
+
+    /// Cancels the token and its children, and wakes up the tasks waiting
+    /// for it.
+    pub fn cancel(&self) {
+        self.node.cancel();
+    }
+
+    /// Whether the token was cancelled.
+    ///
+    /// For the tokens derived from [`CancellationToken::host`], this takes
+    /// the events queued by the host, to check if it cancelled them.
+    pub fn is_cancelled(&self) -> bool {
+        if self.node.host && !self.node.cancelled.load(Ordering::SeqCst) {
+            events::dispatch();
+        }
+        self.node.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Waits until the token is cancelled.
+    pub async fn cancelled(&self) {
+        let mut waiter = Waiter::default();
+        // notified when the host has queued events, for host tokens
+        let mut ready: Option<IoReady> = None;
+        poll_fn(|cx| {
+            if self.node.host {
+                loop {
+                    match &mut ready {
+                        None => ready = Some(Runtime::global().schedule_io(EVENTS_FD)),
+                        Some(notified) => {
+                            if Pin::new(notified).poll(cx).is_pending() {
+                                break;
+                            }
+                            ready = None;
+                        }
+                    }
+                }
+            }
+            waiter.poll_until(&self.node.notify, cx, || self.is_cancelled().then_some(()))
+        })
+        .await
+    }
+
+    /// Runs `future` until it completes, or until the token is cancelled.
+    ///
+    /// Returns `None` if the token was cancelled first, dropping `future`.
+    pub async fn run_until_cancelled<F: Future>(&self, future: F) -> Option<F::Output> {
+        let mut cancelled = pin!(self.cancelled());
+        let mut future = pin!(future);
+        poll_fn(|cx| {
+            if cancelled.as_mut().poll(cx).is_ready() {
+                return Poll::Ready(None);
+            }
+            future.as_mut().poll(cx).map(Some)
+        })
+        .await
+    }
+}
+
+impl Default for CancellationToken {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for CancellationToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CancellationToken")
+            .field("is_cancelled", &self.node.cancelled.load(Ordering::SeqCst))
+            .finish()
+    }
+}
@@ -0,0 +1,19 @@
+//! Synchronization primitives for tasks.
+//!
+//! Waiting on them yields to the runtime instead of spinning, so unlike
+//! `spin::Mutex` their guards can be held across an `.await`.
+
+mod barrier;
+mod cancellation_token;
+mod mutex;
+mod rwlock;
+mod semaphore;
+
+pub use barrier::{Barrier, BarrierWaitResult};
+pub(crate) use cancellation_token::{cancel_host, reset_host};
+pub use cancellation_token::CancellationToken;
+pub use mutex::{Mutex, MutexGuard, TryLockError};
+pub use rwlock::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+pub use semaphore::{
+    AcquireError, OwnedSemaphorePermit, Semaphore, SemaphorePermit, TryAcquireError,
+};
@@ -0,0 +1,124 @@
+use core::cell::UnsafeCell;
+use core::ops::{Deref, DerefMut};
+
+use super::mutex::TryLockError;
+use super::semaphore::Semaphore;
+
+// A reader holds one permit, a writer holds all of them
+const MAX_READS: usize = u32::MAX as usize >> 3;
+
+/// An async reader-writer lock, allowing many readers or a single writer.
+///
+/// Like [`Mutex`](super::Mutex), the guards can be held across an `.await`.
+/// Acquiring isn't fair, so a steady flow of readers can delay a writer.
+pub struct RwLock<T: ?Sized> {
+    semaphore: Semaphore,
+    data: UnsafeCell<T>,
+}
+
+// The semaphore guarantees shared access for readers and exclusive access
+// for writers to `data`
+unsafe impl<T: ?Sized + Send> Send for RwLock<T> {}
+unsafe impl<T: ?Sized + Send + Sync> Sync for RwLock<T> {}
+
+impl<T> RwLock<T> {
+    pub fn new(value: T) -> Self {
+        RwLock {
+            semaphore: Semaphore::new(MAX_READS),
+            data: UnsafeCell::new(value),
+        }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+}
+
+impl<T: ?Sized> RwLock<T> {
+    /// Waits until there's no writer, and locks for reading.
+    pub async fn read(&self) -> RwLockReadGuard<'_, T> {
+        // the semaphore is never closed
+        let permit = self.semaphore.acquire().await.unwrap();
+        permit.forget();
+        RwLockReadGuard { lock: self }
+    }
+
+    /// Waits until there are no readers nor writer, and locks for writing.
+    pub async fn write(&self) -> RwLockWriteGuard<'_, T> {
+        // the semaphore is never closed
+        let permit = self.semaphore.acquire_many(MAX_READS).await.unwrap();
+        permit.forget();
+        RwLockWriteGuard { lock: self }
+    }
+
+    pub fn try_read(&self) -> Result<RwLockReadGuard<'_, T>, TryLockError> {
+        let permit = self.semaphore.try_acquire().map_err(|_| TryLockError)?;
+        permit.forget();
+        Ok(RwLockReadGuard { lock: self })
+    }
+
+    pub fn try_write(&self) -> Result<RwLockWriteGuard<'_, T>, TryLockError> {
+        let permit = self
+            .semaphore
+            .try_acquire_many(MAX_READS)
+            .map_err(|_| TryLockError)?;
+        permit.forget();
+        Ok(RwLockWriteGuard { lock: self })
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+}
+
+impl<T: Default> Default for RwLock<T> {
+    fn default() -> Self {
+        RwLock::new(T::default())
+    }
+}
+
+/// The read lock of a [`RwLock`], released when dropped.
+#[must_use]
+pub struct RwLockReadGuard<'a, T: ?Sized> {
+    lock: &'a RwLock<T>,
+}
+
+impl<T: ?Sized> Deref for RwLockReadGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T: ?Sized> Drop for RwLockReadGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.semaphore.add_permits(1);
+    }
+}
+
+/// The write lock of a [`RwLock`], released when dropped.
+#[must_use]
+pub struct RwLockWriteGuard<'a, T: ?Sized> {
+    lock: &'a RwLock<T>,
+}
+
+impl<T: ?Sized> Deref for RwLockWriteGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T: ?Sized> DerefMut for RwLockWriteGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<T: ?Sized> Drop for RwLockWriteGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.semaphore.add_permits(MAX_READS);
+    }
+}
@@ -0,0 +1,217 @@
+use alloc::sync::Arc;
+use core::fmt;
+use core::future::poll_fn;
+use spin::Mutex;
+
+use crate::notify::{Notify, Waiter};
+
+/// A counting semaphore, to limit how many tasks access a resource at once.
+///
+/// Waiting tasks are woken up together when permits are released, and the
+/// first one to be polled gets them, so acquiring isn't fair.
+pub struct Semaphore {
+    state: Mutex<State>,
+    // notified when permits are released, or the semaphore is closed
+    notify: Notify,
+}
+
+struct State {
+    permits: usize,
+    closed: bool,
+}
+
+/// Error returned when acquiring permits from a closed [`Semaphore`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AcquireError;
+
+/// Error returned when trying to acquire permits without waiting.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TryAcquireError {
+    Closed,
+    NoPermits,
+}
+
+impl fmt::Display for AcquireError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("semaphore closed")
+    }
+}
+
+impl fmt::Display for TryAcquireError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TryAcquireError::Closed => f.write_str("semaphore closed"),
+            TryAcquireError::NoPermits => f.write_str("no permits available"),
+        }
+    }
+}
+
+impl core::error::Error for AcquireError {}
+impl core::error::Error for TryAcquireError {}
+
+impl Semaphore {
+    pub fn new(permits: usize) -> Self {
+        Semaphore {
+            state: Mutex::new(State {
+                permits,
+                closed: false,
+            }),
+            notify: Notify::new(),
+        }
+    }
+
+    pub fn available_permits(&self) -> usize {
+        self.state.lock().permits
+    }
+
+    pub fn add_permits(&self, permits: usize) {
+        self.state.lock().permits += permits;
+        self.notify.notify_waiters();
+    }
+
+    /// Closes the semaphore, making all current and future acquires fail.
+    pub fn close(&self) {
+        self.state.lock().closed = true;
+        self.notify.notify_waiters();
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.state.lock().closed
+    }
+
+    fn try_acquire_inner(&self, permits: usize) -> Result<(), TryAcquireError> {
+        let mut state = self.state.lock();
+        if state.closed {
+            return Err(TryAcquireError::Closed);
+        }
+        if state.permits < permits {
+            return Err(TryAcquireError::NoPermits);
+        }
+        state.permits -= permits;
+        Ok(())
+    }
+
+    async fn acquire_inner(&self, permits: usize) -> Result<(), AcquireError> {
+        let mut waiter = Waiter::default();
+        poll_fn(|cx| {
+            waiter.poll_until(&self.notify, cx, || match self.try_acquire_inner(permits) {
+                Ok(()) => Some(Ok(())),
+                Err(TryAcquireError::Closed) => Some(Err(AcquireError)),
+                Err(TryAcquireError::NoPermits) => None,
+            })
+        })
+        .await
+    }
+
+    pub async fn acquire(&self) -> Result<SemaphorePermit<'_>, AcquireError> {
+        self.acquire_many(1).await
+    }
+
+    pub async fn acquire_many(&self, permits: usize) -> Result<SemaphorePermit<'_>, AcquireError> {
+        self.acquire_inner(permits).await?;
+        Ok(SemaphorePermit {
+            semaphore: self,
+            permits,
+        })
+    }
+
+    pub fn try_acquire(&self) -> Result<SemaphorePermit<'_>, TryAcquireError> {
+        self.try_acquire_many(1)
+    }
+
+    pub fn try_acquire_many(&self, permits: usize) -> Result<SemaphorePermit<'_>, TryAcquireError> {
+        self.try_acquire_inner(permits)?;
+        Ok(SemaphorePermit {
+            semaphore: self,
+            permits,
+        })
+    }
+
+    /// Like [`Semaphore::acquire`], but the permit owns a reference to the
+    /// semaphore, so it can be moved into a spawned task.
+    pub async fn acquire_owned(self: Arc<Self>) -> Result<OwnedSemaphorePermit, AcquireError> {
+        self.acquire_many_owned(1).await
+    }
+
+    pub async fn acquire_many_owned(
+        self: Arc<Self>,
+        permits: usize,
+    ) -> Result<OwnedSemaphorePermit, AcquireError> {
+        self.acquire_inner(permits).await?;
+        Ok(OwnedSemaphorePermit {
+            semaphore: self,
+            permits,
+        })
+    }
+
+    pub fn try_acquire_owned(self: Arc<Self>) -> Result<OwnedSemaphorePermit, TryAcquireError> {
+        self.try_acquire_many_owned(1)
+    }
+
+    pub fn try_acquire_many_owned(
+        self: Arc<Self>,
+        permits: usize,
+    ) -> Result<OwnedSemaphorePermit, TryAcquireError> {
+        self.try_acquire_inner(permits)?;
+        Ok(OwnedSemaphorePermit {
+            semaphore: self,
+            permits,
+        })
+    }
+}
+
+/// Permits acquired from a [`Semaphore`], released when dropped.
+#[must_use]
+pub struct SemaphorePermit<'a> {
+    semaphore: &'a Semaphore,
+    permits: usize,
+}
+
+impl SemaphorePermit<'_> {
+    pub fn num_permits(&self) -> usize {
+        self.permits
+    }
+
+    /// Drops the permits without releasing them.
+    pub fn forget(mut self) {
+        self.permits = 0;
+    }
+}
+
+impl Drop for SemaphorePermit<'_> {
+    fn drop(&mut self) {
+        if self.permits > 0 {
+            self.semaphore.add_permits(self.permits);
+        }
+    }
+}
+
+/// Permits acquired from an `Arc<Semaphore>`, released when dropped.
+#[must_use]
+pub struct OwnedSemaphorePermit {
+    semaphore: Arc<Semaphore>,
+    permits: usize,
+}
+
+impl OwnedSemaphorePermit {
+    pub fn num_permits(&self) -> usize {
+        self.permits
+    }
+
+    pub fn semaphore(&self) -> &Arc<Semaphore> {
+        &self.semaphore
+    }
+
+    /// Drops the permits without releasing them.
+    pub fn forget(mut self) {
+        self.permits = 0;
+    }
+}
+
+impl Drop for OwnedSemaphorePermit {
+    fn drop(&mut self) {
+        if self.permits > 0 {
+            self.semaphore.add_permits(self.permits);
+        }
+    }
+}
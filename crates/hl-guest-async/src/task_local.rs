@@ -0,0 +1,173 @@
+//! Values that are local to a task, declared with [`task_local!`](crate::task_local!).
+
+use core::fmt;
+use core::future::Future;
+use core::mem;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use spin::RwLock;
+
+/// Declares task-local values, of type [`LocalKey`].
+///
+/// ```ignore
+/// task_local! {
+///     static REQUEST_ID: u64;
+/// }
+///
+/// REQUEST_ID.scope(42, async {
+///     assert_eq!(REQUEST_ID.get(), 42);
+/// }).await;
+/// ```
+#[macro_export]
+macro_rules! task_local {
+    () => {};
+    ($(#[$attr:meta])* $vis:vis static $name:ident: $ty:ty; $($rest:tt)*) => {
+        $(#[$attr])*
+        $vis static $name: $crate::task_local::LocalKey<$ty> = $crate::task_local::LocalKey::new();
+        $crate::task_local!($($rest)*);
+    };
+    ($(#[$attr:meta])* $vis:vis static $name:ident: $ty:ty) => {
+        $crate::task_local!($(#[$attr])* $vis static $name: $ty;);
+    };
+}
+
+/// A key for a task-local value.
+///
+/// The value is set for the duration of a future with [`LocalKey::scope`],
+/// or of a closure with [`LocalKey::sync_scope`]. Tasks spawned from a scope
+/// don't inherit its value, wrap their future in a scope to pass it on:
+///
+/// ```ignore
+/// spawn(REQUEST_ID.scope(REQUEST_ID.get(), async { /* ... */ }));
+/// ```
+///
+/// Keys are statics, so the value must be `Send` and `Sync`.
+pub struct LocalKey<T: 'static> {
+    // the value of the scope being polled, only locked for writing while
+    // the value of a scope is moved in or out
+    slot: RwLock<Option<T>>,
+}
+
+impl<T: 'static> LocalKey<T> {
+    #[doc(hidden)]
+    pub const fn new() -> Self {
+        LocalKey {
+            slot: RwLock::new(None),
+        }
+    }
+
+    /// Sets the value to `value` while `future` is polled.
+    pub fn scope<F: Future>(&'static self, value: T, future: F) -> TaskLocalFuture<T, F> {
+        TaskLocalFuture {
+            key: self,
+            slot: Some(value),
+            future,
+        }
+    }
+
+    /// Sets the value to `value` while `f` runs.
+    pub fn sync_scope<R>(&'static self, value: T, f: impl FnOnce() -> R) -> R {
+        let mut slot = Some(value);
+        self.scope_inner(&mut slot, f)
+    }
+
+    // Moves the value of a scope into the key while `f` runs, and back
+    fn scope_inner<R>(&'static self, slot: &mut Option<T>, f: impl FnOnce() -> R) -> R {
+        struct Guard<'a, T: 'static> {
+            key: &'static LocalKey<T>,
+            slot: &'a mut Option<T>,
+        }
+
+        impl<T: 'static> Drop for Guard<'_, T> {
+            fn drop(&mut self) {
+                self.key.swap(self.slot);
+            }
+        }
+
+        self.swap(slot);
+        let _guard = Guard { key: self, slot };
+        f()
+    }
+
+    fn swap(&self, slot: &mut Option<T>) {
+        // waiting for the borrow to end would deadlock, as it's up the stack
+        let Some(mut value) = self.slot.try_write() else {
+            panic!("task-local value set while it's borrowed");
+        };
+        mem::swap(slot, &mut *value);
+    }
+
+    /// Calls `f` with a reference to the value.
+    ///
+    /// # Panics
+    ///
+    /// If it's called outside of a scope of the key.
+    #[track_caller]
+    pub fn with<R>(&'static self, f: impl FnOnce(&T) -> R) -> R {
+        match self.try_with(f) {
+            Ok(result) => result,
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    /// Calls `f` with a reference to the value, or returns an error if it's
+    /// called outside of a scope of the key.
+    pub fn try_with<R>(&'static self, f: impl FnOnce(&T) -> R) -> Result<R, AccessError> {
+        let slot = self.slot.read();
+        match slot.as_ref() {
+            Some(value) => Ok(f(value)),
+            None => Err(AccessError),
+        }
+    }
+
+    /// Returns a copy of the value.
+    ///
+    /// # Panics
+    ///
+    /// If it's called outside of a scope of the key.
+    #[track_caller]
+    pub fn get(&'static self) -> T
+    where
+        T: Clone,
+    {
+        self.with(T::clone)
+    }
+}
+
+impl<T: 'static> fmt::Debug for LocalKey<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad("LocalKey { .. }")
+    }
+}
+
+/// A future with a task-local value set while it's polled, returned by
+/// [`LocalKey::scope`].
+pub struct TaskLocalFuture<T: 'static, F> {
+    key: &'static LocalKey<T>,
+    // the value while the future isn't polled
+    slot: Option<T>,
+    future: F,
+}
+
+impl<T: 'static, F: Future> Future for TaskLocalFuture<T, F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        // `future` is the only field that is structurally pinned
+        let this = unsafe { self.get_unchecked_mut() };
+        let future = unsafe { Pin::new_unchecked(&mut this.future) };
+        this.key.scope_inner(&mut this.slot, || future.poll(cx))
+    }
+}
+
+/// Returned by [`LocalKey::try_with`] outside of a scope of the key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AccessError;
+
+impl fmt::Display for AccessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("task-local value accessed outside of its scope")
+    }
+}
+
+impl core::error::Error for AccessError {}
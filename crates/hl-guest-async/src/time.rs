@@ -1,13 +1,260 @@
-use crate::runtime::Runtime;
+use crate::runtime::{Runtime, Timer};
 
 use super::host::get_time;
-use core::future::Future;
+use core::future::{poll_fn, Future};
+use core::ops::{Add, AddAssign, Sub, SubAssign};
+use core::pin::Pin;
+use core::task::{Context, Poll};
 use core::time::Duration;
-use futures::{select_biased, FutureExt};
+use futures::{select_biased, FutureExt, Stream};
 
-pub async fn sleep(duration: Duration) {
-    let deadline: Duration = get_time() + duration;
-    Runtime::global().schedule_timer(deadline).await;
+/// A measurement of the host's monotonic clock, the one timers are scheduled
+/// on, unaffected by adjustments of the host's wall clock.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Instant(Duration);
+
+impl Instant {
+    pub fn now() -> Instant {
+        Instant(get_time())
+    }
+
+    /// Returns the amount of time elapsed from another instant to this one,
+    /// or zero if that instant is later than this one.
+    pub fn duration_since(&self, earlier: Instant) -> Duration {
+        self.saturating_duration_since(earlier)
+    }
+
+    pub fn checked_duration_since(&self, earlier: Instant) -> Option<Duration> {
+        self.0.checked_sub(earlier.0)
+    }
+
+    pub fn saturating_duration_since(&self, earlier: Instant) -> Duration {
+        self.0.saturating_sub(earlier.0)
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        Instant::now().duration_since(*self)
+    }
+
+    pub fn checked_add(&self, duration: Duration) -> Option<Instant> {
+        self.0.checked_add(duration).map(Instant)
+    }
+
+    pub fn checked_sub(&self, duration: Duration) -> Option<Instant> {
+        self.0.checked_sub(duration).map(Instant)
+    }
+}
+
+impl Add<Duration> for Instant {
+    type Output = Instant;
+
+    fn add(self, other: Duration) -> Instant {
+        self.checked_add(other)
+            .expect("overflow when adding duration to instant")
+    }
+}
+
+impl AddAssign<Duration> for Instant {
+    fn add_assign(&mut self, other: Duration) {
+        *self = *self + other;
+    }
+}
+
+impl Sub<Duration> for Instant {
+    type Output = Instant;
+
+    fn sub(self, other: Duration) -> Instant {
+        self.checked_sub(other)
+            .expect("overflow when subtracting duration from instant")
+    }
+}
+
+impl SubAssign<Duration> for Instant {
+    fn sub_assign(&mut self, other: Duration) {
+        *self = *self - other;
+    }
+}
+
+impl Sub<Instant> for Instant {
+    type Output = Duration;
+
+    fn sub(self, other: Instant) -> Duration {
+        self.duration_since(other)
+    }
+}
+
+/// Waits until `duration` has elapsed.
+pub fn sleep(duration: Duration) -> Sleep {
+    sleep_until(Instant::now() + duration)
+}
+
+/// Waits until `deadline` is reached.
+pub fn sleep_until(deadline: Instant) -> Sleep {
+    Sleep {
+        deadline,
+        timer: None,
+    }
+}
+
+/// Future returned by [`sleep`] and [`sleep_until`].
+///
+/// The timer is only scheduled when the future is first polled, and can be
+/// moved to a new deadline with [`Sleep::reset`]. Dropping the future cancels
+/// the timer.
+pub struct Sleep {
+    deadline: Instant,
+    timer: Option<Timer>,
+}
+
+impl Sleep {
+    pub fn deadline(&self) -> Instant {
+        self.deadline
+    }
+
+    pub fn is_elapsed(&self) -> bool {
+        Instant::now() >= self.deadline
+    }
+
+    /// Resets the sleep to complete at `deadline` instead, even if it
+    /// already completed.
+    pub fn reset(&mut self, deadline: Instant) {
+        self.deadline = deadline;
+        self.timer = None;
+    }
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        loop {
+            if let Some(timer) = &mut self.timer {
+                if Pin::new(timer).poll(cx).is_pending() {
+                    return Poll::Pending;
+                }
+                self.timer = None;
+            }
+            if self.is_elapsed() {
+                return Poll::Ready(());
+            }
+            let deadline = self.deadline.0;
+            self.timer = Some(Runtime::global().schedule_timer(deadline));
+        }
+    }
+}
+
+/// What an [`Interval`] does when a tick is late, e.g., because the task
+/// was busy.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MissedTickBehavior {
+    /// Ticks as fast as possible until it catches up with the original
+    /// schedule.
+    #[default]
+    Burst,
+    /// Schedules the next tick one period after the late one.
+    Delay,
+    /// Skips the missed ticks and ticks at the next multiple of the period
+    /// of the original schedule.
+    Skip,
+}
+
+impl MissedTickBehavior {
+    // The deadline of the tick after the one scheduled at `deadline`, which
+    // completed at `now`.
+    fn next_deadline(&self, deadline: Instant, now: Instant, period: Duration) -> Instant {
+        match self {
+            MissedTickBehavior::Burst => deadline + period,
+            MissedTickBehavior::Delay => now + period,
+            MissedTickBehavior::Skip => {
+                let behind = (now - deadline).as_nanos() % period.as_nanos();
+                now + period - Duration::from_nanos(behind as u64)
+            }
+        }
+    }
+}
+
+/// Ticks are considered missed when they complete later than this.
+const MISSED_TICK_THRESHOLD: Duration = Duration::from_millis(5);
+
+/// Creates an [`Interval`] that ticks immediately, and then every `period`.
+///
+/// # Panics
+///
+/// Panics if `period` is zero.
+pub fn interval(period: Duration) -> Interval {
+    interval_at(Instant::now(), period)
+}
+
+/// Creates an [`Interval`] that ticks at `start`, and then every `period`.
+///
+/// # Panics
+///
+/// Panics if `period` is zero.
+pub fn interval_at(start: Instant, period: Duration) -> Interval {
+    assert!(!period.is_zero(), "`period` must be non-zero");
+    Interval {
+        sleep: sleep_until(start),
+        period,
+        missed_tick_behavior: MissedTickBehavior::default(),
+    }
+}
+
+/// A periodic timer, created with [`interval`] or [`interval_at`].
+///
+/// The ticks are scheduled on a fixed schedule, so they don't drift like a
+/// loop of [`sleep`]s. It's also a [`Stream`] of the tick instants.
+pub struct Interval {
+    sleep: Sleep,
+    period: Duration,
+    missed_tick_behavior: MissedTickBehavior,
+}
+
+impl Interval {
+    /// Waits for the next tick, and returns the instant it was scheduled at.
+    pub async fn tick(&mut self) -> Instant {
+        poll_fn(|cx| self.poll_tick(cx)).await
+    }
+
+    pub fn poll_tick(&mut self, cx: &mut Context<'_>) -> Poll<Instant> {
+        if Pin::new(&mut self.sleep).poll(cx).is_pending() {
+            return Poll::Pending;
+        }
+        let deadline = self.sleep.deadline();
+        let now = Instant::now();
+        let next = if now > deadline + MISSED_TICK_THRESHOLD {
+            self.missed_tick_behavior
+                .next_deadline(deadline, now, self.period)
+        } else {
+            deadline + self.period
+        };
+        self.sleep.reset(next);
+        Poll::Ready(deadline)
+    }
+
+    /// Resets the interval so that the next tick is one period from now.
+    pub fn reset(&mut self) {
+        self.sleep.reset(Instant::now() + self.period);
+    }
+
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    pub fn missed_tick_behavior(&self) -> MissedTickBehavior {
+        self.missed_tick_behavior
+    }
+
+    pub fn set_missed_tick_behavior(&mut self, behavior: MissedTickBehavior) {
+        self.missed_tick_behavior = behavior;
+    }
+}
+
+impl Stream for Interval {
+    type Item = Instant;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Instant>> {
+        self.poll_tick(cx).map(Some)
+    }
 }
 
 pub trait Timeout: Future {
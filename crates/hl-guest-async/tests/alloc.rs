@@ -0,0 +1,133 @@
+//! Counts the allocations of the loops that `guest-bench` times, as the guest
+//! can't replace the allocator of `hyperlight-guest` to count them itself.
+
+mod common;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::future::Future;
+
+use hl_guest_async::channel::mpsc;
+use hl_guest_async::notify::Notify;
+use hl_guest_async::{spawn, yield_now, JoinSet};
+
+use common::block_on;
+
+const ITERATIONS: u32 = 1_000;
+
+// Counts the allocations of the current thread, as the test harness can
+// allocate from other threads at the same time
+struct Counting;
+
+thread_local! {
+    static ALLOCATIONS: Cell<u64> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+// Runs `iterations`, which loops `ITERATIONS` times, and returns the number
+// of allocations it made, after a first run that lets the runtime grow its
+// queues
+fn allocations(iterations: impl Fn() -> Box<dyn Future<Output = ()> + Unpin>) -> u64 {
+    block_on(async {
+        iterations().await;
+        let iterations = iterations();
+        let before = ALLOCATIONS.with(Cell::get);
+        iterations.await;
+        ALLOCATIONS.with(Cell::get) - before
+    })
+}
+
+#[test]
+fn allocations_per_iteration() {
+    let spawn_await = allocations(|| {
+        Box::new(Box::pin(async {
+            for i in 0..ITERATIONS {
+                assert_eq!(spawn(async move { i }).await, Ok(i));
+            }
+        }))
+    });
+
+    let join_set = allocations(|| {
+        Box::new(Box::pin(async {
+            for i in 0..ITERATIONS {
+                let mut set = JoinSet::new();
+                for j in 0..16 {
+                    set.spawn(async move { i + j });
+                }
+                while set.join_next().await.is_some() {}
+            }
+        }))
+    });
+
+    let yield_now = allocations(|| {
+        Box::new(Box::pin(async {
+            for _ in 0..ITERATIONS {
+                yield_now().await;
+            }
+        }))
+    });
+
+    let mpsc = allocations(|| {
+        Box::new(Box::pin(async {
+            let (ping, mut pings) = mpsc::channel(1);
+            let (pong, mut pongs) = mpsc::channel(1);
+            let echo = spawn(async move {
+                while let Ok(i) = pings.recv().await {
+                    pong.send(i).await.unwrap();
+                }
+            });
+            for i in 0..ITERATIONS {
+                ping.send(i).await.unwrap();
+                assert_eq!(pongs.recv().await, Ok(i));
+            }
+            drop(ping);
+            echo.await.unwrap();
+        }))
+    });
+
+    let notify = allocations(|| {
+        Box::new(Box::pin(async {
+            let notify = Notify::new();
+            for _ in 0..ITERATIONS {
+                let notified = notify.notified();
+                notify.notify_one();
+                notified.await;
+            }
+        }))
+    });
+
+    for (name, allocations) in [
+        ("spawn + await", spawn_await),
+        ("JoinSet of 16 tasks", join_set),
+        ("yield_now", yield_now),
+        ("mpsc ping-pong", mpsc),
+        ("Notify", notify),
+    ] {
+        let per_iteration = allocations as f64 / ITERATIONS as f64;
+        println!("{name:<20} {per_iteration:>6.2} allocations/iter");
+    }
+
+    let iterations = ITERATIONS as u64;
+    // the task itself
+    assert!(spawn_await <= iterations);
+    // the tasks, and the nodes of the set
+    assert!(join_set <= 34 * iterations);
+    assert_eq!(yield_now, 0);
+    // the waiter of each side, and once the channels and the echo task
+    assert!(mpsc <= 2 * iterations + 7);
+    // the waiter, and once the `Notify`
+    assert!(notify <= iterations + 1);
+}
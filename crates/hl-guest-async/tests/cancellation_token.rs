@@ -0,0 +1,101 @@
+mod common;
+
+use std::time::Duration;
+
+use hl_guest_async::events::CANCEL_EVENT;
+use hl_guest_async::sync::CancellationToken;
+use hl_guest_async::time::sleep;
+use hl_guest_async::{spawn, yield_now};
+
+use common::{block_on, push_event};
+
+#[test]
+fn cancelling_a_token_cancels_its_children() {
+    block_on(async {
+        let parent = CancellationToken::new();
+        let child = parent.child_token();
+        let grandchild = child.child_token();
+
+        child.cancel();
+        assert!(!parent.is_cancelled());
+        assert!(child.is_cancelled());
+        assert!(grandchild.is_cancelled());
+
+        parent.cancel();
+        assert!(parent.child_token().is_cancelled());
+    });
+}
+
+#[test]
+fn cancelling_a_token_wakes_its_waiters() {
+    block_on(async {
+        let token = CancellationToken::new();
+        let waiter = spawn({
+            let token = token.child_token();
+            async move { token.cancelled().await }
+        });
+        let run = spawn({
+            let token = token.clone();
+            async move {
+                token
+                    .run_until_cancelled(sleep(Duration::from_secs(3600)))
+                    .await
+            }
+        });
+        yield_now().await;
+        assert!(!waiter.is_finished());
+        assert!(!run.is_finished());
+
+        token.cancel();
+        waiter.await.unwrap();
+        assert_eq!(run.await, Ok(None));
+        assert_eq!(token.run_until_cancelled(async { 1 }).await, None);
+    });
+}
+
+#[test]
+fn run_until_cancelled_returns_the_output() {
+    block_on(async {
+        let token = CancellationToken::new();
+        assert_eq!(token.run_until_cancelled(async { 1 }).await, Some(1));
+    });
+}
+
+#[test]
+fn host_cancels_its_token_with_an_event() {
+    block_on(async {
+        hl_guest_async::begin_call();
+        let token = CancellationToken::host();
+        let child = token.child_token();
+        let waiter = spawn(async move { child.cancelled().await });
+        yield_now().await;
+        assert!(!token.is_cancelled());
+        assert!(!waiter.is_finished());
+
+        push_event(CANCEL_EVENT, &[]);
+        waiter.await.unwrap();
+        assert!(token.is_cancelled());
+    });
+}
+
+#[test]
+fn each_call_gets_a_fresh_host_token() {
+    block_on(async {
+        hl_guest_async::begin_call();
+        let first = CancellationToken::host();
+        // the same call keeps the same token
+        hl_guest_async::begin_call();
+        let second = CancellationToken::host();
+        first.cancel();
+        assert!(second.is_cancelled());
+
+        hl_guest_async::begin_call();
+        let third = CancellationToken::host();
+        assert!(!third.is_cancelled());
+        assert!(first.is_cancelled());
+
+        push_event(CANCEL_EVENT, &[]);
+        assert!(third.is_cancelled());
+        assert!(first.is_cancelled());
+    });
+}
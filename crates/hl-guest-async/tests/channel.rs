@@ -0,0 +1,128 @@
+mod common;
+
+use std::time::Duration;
+
+use futures::SinkExt as _;
+use hl_guest_async::channel::{broadcast, mpsc, oneshot, watch, RecvError, TryRecvError};
+use hl_guest_async::time::sleep;
+use hl_guest_async::{join, spawn, FutureExt as _};
+
+use common::block_on;
+
+#[test]
+fn mpsc_closes_when_all_senders_drop() {
+    block_on(async {
+        let (tx, mut rx) = mpsc::channel(4);
+        let tx2 = tx.clone();
+        tx.send(1).await.unwrap();
+        drop(tx);
+        tx2.send(2).await.unwrap();
+        drop(tx2);
+        assert_eq!(rx.recv().await, Ok(1));
+        assert_eq!(rx.recv().await, Ok(2));
+        assert_eq!(rx.recv().await, Err(RecvError::Closed));
+    });
+}
+
+#[test]
+fn mpsc_closed_wakes_a_waiting_receiver() {
+    block_on(async {
+        let (tx, mut rx) = mpsc::unbounded_channel::<u32>();
+        let receiver = spawn(async move { rx.recv().await });
+        sleep(Duration::from_millis(1)).await;
+        drop(tx);
+        assert_eq!(receiver.await.unwrap(), Err(RecvError::Closed));
+    });
+}
+
+#[test]
+fn bounded_send_waits_for_room() {
+    block_on(async {
+        let (tx, mut rx) = mpsc::channel(2);
+        tx.send(1).await.unwrap();
+        tx.send(2).await.unwrap();
+        assert!(tx.try_send(3).is_err());
+
+        let sender = spawn(async move {
+            tx.send(3).await.unwrap();
+        });
+        sleep(Duration::from_millis(1)).await;
+        assert!(!sender.is_finished());
+
+        assert_eq!(rx.recv().await, Ok(1));
+        sender.await.unwrap();
+        assert_eq!(rx.recv().await, Ok(2));
+        assert_eq!(rx.recv().await, Ok(3));
+    });
+}
+
+#[test]
+fn bounded_sink_respects_capacity() {
+    block_on(async {
+        let (mut tx1, mut rx) = mpsc::channel(1);
+        let tx2 = tx1.clone();
+        // the first sender holds the only room, the second must wait for it
+        futures::future::poll_fn(|cx| tx1.poll_ready_unpin(cx))
+            .await
+            .unwrap();
+        assert!(tx2.send(2).now_or_never().is_none());
+        tx1.start_send_unpin(1).unwrap();
+        assert_eq!(rx.try_recv(), Ok(1));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+
+        let (sent, received) = join!(tx2.send(2), rx.recv());
+        sent.unwrap();
+        assert_eq!(received, Ok(2));
+    });
+}
+
+#[test]
+fn broadcast_lags_slow_receivers() {
+    block_on(async {
+        let (tx, mut rx) = broadcast::channel(2);
+        for i in 0..5 {
+            tx.send(i).unwrap();
+        }
+        assert_eq!(rx.recv().await, Err(broadcast::RecvError::Lagged(3)));
+        assert_eq!(rx.recv().await, Ok(3));
+        assert_eq!(rx.recv().await, Ok(4));
+        drop(tx);
+        assert_eq!(rx.recv().await, Err(broadcast::RecvError::Closed));
+    });
+}
+
+#[test]
+fn watch_changed_sees_the_latest_value() {
+    block_on(async {
+        let (tx, mut rx) = watch::channel(0);
+        assert!(!rx.has_changed().unwrap());
+
+        let watcher = spawn(async move {
+            rx.changed().await.unwrap();
+            let value = *rx.borrow_and_update();
+            (value, rx)
+        });
+        sleep(Duration::from_millis(1)).await;
+        tx.send(1).unwrap();
+        tx.send(2).unwrap();
+        let (value, mut rx) = watcher.await.unwrap();
+        assert_eq!(value, 2);
+
+        drop(tx);
+        assert_eq!(rx.changed().await, Err(RecvError::Closed));
+    });
+}
+
+#[test]
+fn oneshot_fails_when_the_sender_drops() {
+    block_on(async {
+        let (tx, mut rx) = oneshot::channel::<u32>();
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+        drop(tx);
+        assert_eq!(rx.await, Err(RecvError::Closed));
+
+        let (tx, rx) = oneshot::channel();
+        tx.send(1).unwrap();
+        assert_eq!(rx.await, Ok(1));
+    });
+}
@@ -0,0 +1,124 @@
+//! Stands in for the host calls that `hl-guest` provides in a guest, so that
+//! the runtime can run natively: the clock is real, stdin never has input
+//! unless a test provides it with [`set_stdin`], and the host only queues
+//! the events of [`push_event`].
+
+#![allow(dead_code)]
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::{Mutex, OnceLock, PoisonError};
+use std::time::{Duration, Instant};
+
+use hl_guest_async::events::EVENTS_FD;
+
+#[repr(C)]
+struct Timespec {
+    tv_sec: i64,
+    tv_nsec: i64,
+}
+
+impl Timespec {
+    fn duration(&self) -> Duration {
+        Duration::new(self.tv_sec as _, self.tv_nsec as _)
+    }
+}
+
+#[repr(C)]
+struct Buffer {
+    ptr: *mut u8,
+    len: usize,
+    capacity: usize,
+}
+
+#[no_mangle]
+extern "C" fn __monotonic() -> Timespec {
+    static START: OnceLock<Instant> = OnceLock::new();
+    let elapsed = START.get_or_init(Instant::now).elapsed();
+    Timespec {
+        tv_sec: elapsed.as_secs() as _,
+        tv_nsec: elapsed.subsec_nanos() as _,
+    }
+}
+
+// The input left, once a test provided it
+static STDIN: Mutex<Option<VecDeque<u8>>> = Mutex::new(None);
+
+/// Makes `input` the input of stdin, which is closed once it's read.
+pub fn set_stdin(input: &str) {
+    *STDIN.lock().unwrap() = Some(input.bytes().collect());
+}
+
+#[no_mangle]
+extern "C" fn __try_read(_fd: i32, buffer: *mut u8, len: usize) -> i32 {
+    let mut stdin = STDIN.lock().unwrap();
+    let Some(input) = stdin.as_mut() else {
+        // no input yet
+        return -2;
+    };
+    let buffer = unsafe { std::slice::from_raw_parts_mut(buffer, len) };
+    let n = len.min(input.len());
+    for (byte, c) in buffer.iter_mut().zip(input.drain(..n)) {
+        *byte = c;
+    }
+    n as _
+}
+
+#[no_mangle]
+extern "C" fn __poll_read(fds: *mut i32, nfds: usize, timeout: Timespec) -> i32 {
+    let fds = unsafe { std::slice::from_raw_parts_mut(fds, nfds) };
+    // stdin has input, or is closed
+    let stdin_ready = STDIN.lock().unwrap().is_some();
+    let events_ready = !EVENTS.lock().unwrap().is_empty();
+    let is_ready = |fd: i32| (fd == 0 && stdin_ready) || (fd == EVENTS_FD && events_ready);
+    if fds.iter().any(|fd| is_ready(*fd)) {
+        let mut count = 0;
+        for fd in fds.iter_mut() {
+            if is_ready(*fd) {
+                count += 1;
+            } else {
+                *fd = -1;
+            }
+        }
+        return count;
+    }
+    // the host counts whole microseconds, and a zero timeout waits forever,
+    // which would hang the test, abort instead
+    assert!(
+        timeout.duration().as_micros() != 0,
+        "waiting for input that never comes"
+    );
+    std::thread::sleep(timeout.duration());
+    fds.fill(-1);
+    0
+}
+
+// The events queued by the tests, encoded like the result of `NextEvent`
+static EVENTS: Mutex<VecDeque<Vec<u8>>> = Mutex::new(VecDeque::new());
+
+/// Queues an event, as if the host pushed it.
+pub fn push_event(name: &str, data: &[u8]) {
+    let mut event = (name.len() as u32).to_le_bytes().to_vec();
+    event.extend_from_slice(name.as_bytes());
+    event.extend_from_slice(data);
+    EVENTS.lock().unwrap().push_back(event);
+}
+
+#[no_mangle]
+extern "C" fn __next_event() -> Buffer {
+    let event = EVENTS.lock().unwrap().pop_front().unwrap_or_default();
+    let mut event = std::mem::ManuallyDrop::new(event);
+    Buffer {
+        ptr: event.as_mut_ptr(),
+        len: event.len(),
+        capacity: event.capacity(),
+    }
+}
+
+/// Runs `future` to completion on the runtime, which is global, so tests
+/// take turns.
+pub fn block_on<T>(future: impl Future<Output = T>) -> T {
+    static LOCK: Mutex<()> = Mutex::new(());
+    let _guard = LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+    hl_guest_async::block_on(future)
+}
@@ -0,0 +1,38 @@
+mod common;
+
+use std::time::Duration;
+
+use hl_guest_async::channel::mpsc;
+use hl_guest_async::events;
+use hl_guest_async::io::stdin;
+use hl_guest_async::time::Timeout as _;
+
+use common::block_on;
+
+#[test]
+#[should_panic(expected = "deadlock")]
+fn detected_after_a_cancelled_read() {
+    block_on(async {
+        let (_tx, mut rx) = mpsc::channel::<u32>(1);
+        let stdin = stdin();
+        let line = stdin
+            .read_line_to_string()
+            .timeout(Duration::from_millis(10));
+        assert!(line.await.is_none());
+        // the read was dropped, so nothing can wake us up anymore
+        let _ = rx.recv().await;
+    });
+}
+
+#[test]
+#[should_panic(expected = "deadlock")]
+fn detected_after_an_unsubscribe() {
+    block_on(async {
+        let (_tx, mut rx) = mpsc::channel::<u32>(1);
+        let mut subscription = events::subscribe("event");
+        let event = subscription.recv().timeout(Duration::from_millis(10));
+        assert!(event.await.is_none());
+        drop(subscription);
+        let _ = rx.recv().await;
+    });
+}
@@ -0,0 +1,43 @@
+mod common;
+
+use hl_guest_async::io::stdin;
+use hl_guest_async::StreamExt as _;
+
+use common::{block_on, set_stdin};
+
+#[test]
+fn lines_end_when_stdin_is_closed() {
+    block_on(async {
+        set_stdin("a\r\nb\n\nlast");
+        let lines: Vec<_> = stdin().lines().map(Result::unwrap).collect().await;
+        assert_eq!(lines, ["a", "b", "", "last"]);
+
+        let mut lines = stdin().lines();
+        assert!(lines.next().await.is_none());
+        assert!(lines.next().await.is_none());
+    });
+}
+
+#[test]
+fn lines_leave_the_rest_of_the_input_to_other_readers() {
+    block_on(async {
+        set_stdin("a\nb\nc\n");
+        let mut lines = stdin().lines();
+        assert_eq!(lines.next().await.unwrap().unwrap(), "a");
+        assert_eq!(stdin().read_line_to_string().await.unwrap(), "b\n");
+        assert_eq!(lines.next().await.unwrap().unwrap(), "c");
+        assert!(lines.next().await.is_none());
+    });
+}
+
+#[test]
+fn read_returns_zero_once_stdin_is_closed() {
+    block_on(async {
+        set_stdin("ab");
+        let mut buf = [0; 4];
+        assert_eq!(stdin().read(&mut buf).await.unwrap(), 2);
+        assert_eq!(&buf[..2], b"ab");
+        assert_eq!(stdin().read(&mut buf).await.unwrap(), 0);
+        assert_eq!(stdin().read_line_to_string().await.unwrap(), "");
+    });
+}
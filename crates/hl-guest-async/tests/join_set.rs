@@ -0,0 +1,98 @@
+mod common;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use hl_guest_async::time::sleep;
+use hl_guest_async::{spawn, yield_now, JoinError, JoinSet};
+
+use common::block_on;
+
+#[test]
+fn join_next_returns_tasks_in_completion_order() {
+    block_on(async {
+        let mut set = JoinSet::new();
+        for ms in [3, 1, 2] {
+            set.spawn(async move {
+                sleep(Duration::from_millis(ms)).await;
+                ms
+            });
+        }
+        assert_eq!(set.len(), 3);
+        let mut order = Vec::new();
+        while let Some(result) = set.join_next().await {
+            order.push(result.unwrap());
+        }
+        assert_eq!(order, [1, 2, 3]);
+        assert!(set.is_empty());
+        assert_eq!(set.join_next().await, None);
+    });
+}
+
+#[test]
+fn aborted_task_joins_as_cancelled() {
+    block_on(async {
+        let mut set = JoinSet::new();
+        let abort = set.spawn(async {
+            sleep(Duration::from_secs(3600)).await;
+            1
+        });
+        set.spawn(async { 2 });
+        abort.abort();
+
+        let mut results = Vec::new();
+        while let Some(result) = set.join_next().await {
+            results.push(result);
+        }
+        results.sort_by_key(|result| result.is_ok());
+        assert_eq!(results, [Err(JoinError::Cancelled), Ok(2)]);
+        assert!(results[0].unwrap_err().is_cancelled());
+    });
+}
+
+#[test]
+fn shutdown_aborts_all_tasks() {
+    block_on(async {
+        let mut set = JoinSet::new();
+        for _ in 0..3 {
+            set.spawn(async { sleep(Duration::from_secs(3600)).await });
+        }
+        set.shutdown().await;
+        assert!(set.is_empty());
+    });
+}
+
+#[test]
+fn dropping_the_set_aborts_its_tasks() {
+    block_on(async {
+        let done = Arc::new(AtomicBool::new(false));
+        let mut set = JoinSet::new();
+        set.spawn({
+            let done = done.clone();
+            async move {
+                sleep(Duration::from_millis(1)).await;
+                done.store(true, Ordering::Relaxed);
+            }
+        });
+        drop(set);
+        sleep(Duration::from_millis(5)).await;
+        assert!(!done.load(Ordering::Relaxed));
+    });
+}
+
+#[test]
+fn abort_after_completion_keeps_the_result() {
+    block_on(async {
+        let handle = spawn(async { 1 });
+        while !handle.is_finished() {
+            yield_now().await;
+        }
+        handle.abort();
+        assert_eq!(handle.await, Ok(1));
+
+        let handle = spawn(async { sleep(Duration::from_secs(3600)).await });
+        handle.abort();
+        assert_eq!(handle.await, Err(JoinError::Cancelled));
+    });
+}
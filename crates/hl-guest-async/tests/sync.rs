@@ -0,0 +1,159 @@
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use hl_guest_async::sync::{AcquireError, Barrier, Mutex, Semaphore, TryAcquireError};
+use hl_guest_async::time::sleep;
+use hl_guest_async::{spawn, yield_now};
+
+use common::block_on;
+
+#[test]
+fn semaphore_limits_the_permits_held() {
+    block_on(async {
+        let semaphore = Arc::new(Semaphore::new(2));
+        let held = Arc::new(Mutex::new((0, 0)));
+        let tasks: Vec<_> = (0..6)
+            .map(|_| {
+                let semaphore = semaphore.clone();
+                let held = held.clone();
+                spawn(async move {
+                    let _permit = semaphore.acquire().await.unwrap();
+                    {
+                        let mut held = held.lock().await;
+                        held.0 += 1;
+                        held.1 = held.1.max(held.0);
+                    }
+                    sleep(Duration::from_millis(1)).await;
+                    held.lock().await.0 -= 1;
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+        assert_eq!(*held.lock().await, (0, 2));
+        assert_eq!(semaphore.available_permits(), 2);
+    });
+}
+
+#[test]
+fn semaphore_permits_are_released_unless_forgotten() {
+    block_on(async {
+        let semaphore = Semaphore::new(3);
+        let permit = semaphore.acquire_many(2).await.unwrap();
+        assert_eq!(permit.num_permits(), 2);
+        assert_eq!(
+            semaphore.try_acquire_many(2).err(),
+            Some(TryAcquireError::NoPermits)
+        );
+        drop(permit);
+        assert_eq!(semaphore.available_permits(), 3);
+
+        semaphore.acquire().await.unwrap().forget();
+        assert_eq!(semaphore.available_permits(), 2);
+        semaphore.add_permits(1);
+        assert_eq!(semaphore.available_permits(), 3);
+    });
+}
+
+#[test]
+fn closing_a_semaphore_wakes_its_waiters() {
+    block_on(async {
+        let semaphore = Arc::new(Semaphore::new(0));
+        let waiter = spawn({
+            let semaphore = semaphore.clone();
+            async move { semaphore.acquire().await.map(|_| ()) }
+        });
+        yield_now().await;
+        assert!(!waiter.is_finished());
+        semaphore.close();
+        assert_eq!(waiter.await.unwrap(), Err(AcquireError));
+        assert_eq!(semaphore.try_acquire().err(), Some(TryAcquireError::Closed));
+    });
+}
+
+#[test]
+fn mutex_guard_is_held_across_await() {
+    block_on(async {
+        let mutex = Arc::new(Mutex::new(Vec::new()));
+        let tasks: Vec<_> = (0..3)
+            .map(|i| {
+                let mutex = mutex.clone();
+                spawn(async move {
+                    let mut values = mutex.lock().await;
+                    values.push(i);
+                    // nobody else can push while we sleep
+                    sleep(Duration::from_millis(1)).await;
+                    values.push(i);
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+        let values = mutex.lock().await;
+        assert_eq!(values.len(), 6);
+        for pair in values.chunks(2) {
+            assert_eq!(pair[0], pair[1]);
+        }
+    });
+}
+
+#[test]
+fn mutex_try_lock_fails_while_locked() {
+    block_on(async {
+        let mutex = Mutex::new(1);
+        let guard = mutex.lock().await;
+        assert!(mutex.try_lock().is_err());
+        drop(guard);
+        *mutex.try_lock().unwrap() += 1;
+        assert_eq!(mutex.into_inner(), 2);
+    });
+}
+
+#[test]
+fn barrier_releases_all_tasks_with_one_leader() {
+    block_on(async {
+        let barrier = Arc::new(Barrier::new(3));
+        let passed = Arc::new(Mutex::new(0));
+        let tasks: Vec<_> = (0..3)
+            .map(|_| {
+                let barrier = barrier.clone();
+                let passed = passed.clone();
+                spawn(async move {
+                    let result = barrier.wait().await;
+                    *passed.lock().await += 1;
+                    result.is_leader()
+                })
+            })
+            .collect();
+
+        let mut leaders = 0;
+        for task in tasks {
+            if task.await.unwrap() {
+                leaders += 1;
+            }
+        }
+        assert_eq!(leaders, 1);
+        assert_eq!(*passed.lock().await, 3);
+    });
+}
+
+#[test]
+fn barrier_waits_for_all_tasks_and_is_reusable() {
+    block_on(async {
+        let barrier = Arc::new(Barrier::new(2));
+        for _ in 0..2 {
+            let waiter = spawn({
+                let barrier = barrier.clone();
+                async move { barrier.wait().await.is_leader() }
+            });
+            sleep(Duration::from_millis(1)).await;
+            assert!(!waiter.is_finished());
+            assert!(barrier.wait().await.is_leader());
+            assert!(!waiter.await.unwrap());
+        }
+    });
+}
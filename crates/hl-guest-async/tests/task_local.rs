@@ -0,0 +1,91 @@
+mod common;
+
+use std::time::Duration;
+
+use hl_guest_async::task_local::AccessError;
+use hl_guest_async::time::sleep;
+use hl_guest_async::{spawn, task_local, yield_now};
+
+use common::block_on;
+
+task_local! {
+    static ID: u32;
+    static NAME: String;
+}
+
+#[test]
+fn value_is_only_set_in_its_scope() {
+    block_on(async {
+        assert_eq!(ID.try_with(|id| *id), Err(AccessError));
+        ID.scope(1, async {
+            assert_eq!(ID.get(), 1);
+            assert_eq!(ID.with(|id| id + 1), 2);
+        })
+        .await;
+        assert_eq!(ID.try_with(|id| *id), Err(AccessError));
+
+        let len = NAME.sync_scope("task".to_string(), || NAME.with(String::len));
+        assert_eq!(len, 4);
+        assert!(NAME.try_with(String::len).is_err());
+    });
+}
+
+#[test]
+fn nested_scopes_are_restored_across_await() {
+    block_on(async {
+        ID.scope(1, async {
+            ID.scope(2, async {
+                yield_now().await;
+                assert_eq!(ID.get(), 2);
+                sleep(Duration::from_millis(1)).await;
+                assert_eq!(ID.get(), 2);
+            })
+            .await;
+            assert_eq!(ID.get(), 1);
+            yield_now().await;
+            assert_eq!(ID.get(), 1);
+        })
+        .await;
+    });
+}
+
+#[test]
+fn interleaved_tasks_keep_their_value() {
+    block_on(async {
+        let tasks: Vec<_> = (0..3)
+            .map(|i| {
+                spawn(ID.scope(i, async move {
+                    for _ in 0..3 {
+                        assert_eq!(ID.get(), i);
+                        yield_now().await;
+                    }
+                    ID.get()
+                }))
+            })
+            .collect();
+        for (i, task) in tasks.into_iter().enumerate() {
+            assert_eq!(task.await, Ok(i as u32));
+        }
+    });
+}
+
+#[test]
+fn spawned_tasks_do_not_inherit_the_value() {
+    block_on(async {
+        ID.scope(1, async {
+            let task = spawn(async { ID.try_with(|id| *id) });
+            assert_eq!(task.await, Ok(Err(AccessError)));
+        })
+        .await;
+    });
+}
+
+#[test]
+fn nested_with_can_read_the_value() {
+    block_on(async {
+        ID.scope(1, async {
+            assert_eq!(ID.with(|outer| ID.with(|inner| outer + inner)), 2);
+        })
+        .await;
+    });
+}
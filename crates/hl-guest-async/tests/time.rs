@@ -0,0 +1,62 @@
+mod common;
+
+use std::pin::pin;
+use std::time::Duration;
+
+use futures::poll;
+use hl_guest_async::debug::snapshot;
+use hl_guest_async::time::{sleep, Timeout as _};
+
+use common::block_on;
+
+#[test]
+fn dropped_sleep_removes_its_timer() {
+    block_on(async {
+        let mut sleep = Box::pin(sleep(Duration::from_secs(3600)));
+        // the timer is only scheduled once the sleep is polled
+        assert!(snapshot().timers.is_empty());
+        assert!(poll!(sleep.as_mut()).is_pending());
+        assert_eq!(snapshot().timers.len(), 1);
+        drop(sleep);
+        assert!(snapshot().timers.is_empty());
+    });
+}
+
+#[test]
+fn reset_sleep_replaces_its_timer() {
+    block_on(async {
+        let mut sleep = pin!(sleep(Duration::from_secs(3600)));
+        assert!(poll!(sleep.as_mut()).is_pending());
+        let deadline = sleep.deadline() + Duration::from_secs(3600);
+        sleep.as_mut().reset(deadline);
+        assert!(poll!(sleep.as_mut()).is_pending());
+        let timers = snapshot().timers;
+        assert_eq!(timers.len(), 1);
+        assert!(timers[0] > Duration::from_secs(3600));
+    });
+}
+
+#[test]
+fn completed_timeout_removes_its_timer() {
+    block_on(async {
+        assert_eq!(
+            async { 1 }.timeout(Duration::from_secs(3600)).await,
+            Some(1)
+        );
+        assert!(snapshot().timers.is_empty());
+    });
+}
+
+#[test]
+fn sub_microsecond_sleep_completes() {
+    block_on(async {
+        // the host counts whole microseconds, and waits forever for zero
+        for nanos in [1, 500, 1500] {
+            for _ in 0..100 {
+                sleep(Duration::from_nanos(nanos)).await;
+            }
+        }
+        let pending = std::future::pending::<()>();
+        assert_eq!(pending.timeout(Duration::from_nanos(500)).await, None);
+    });
+}
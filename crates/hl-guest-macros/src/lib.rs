@@ -111,6 +111,7 @@ pub fn guest_function(attr: TokenStream, item: TokenStream) -> TokenStream {
                 use #crate_name::__private::hyperlight_guest::guest_function_register::register_function;
 
                 fn wrapper(function_call: &FunctionCall) -> ::core::result::Result<Vec<u8>, HyperlightGuestError> {
+                    #crate_name::__private::begin_call();
                     static EMPTY_VEC: Vec<ParameterValue> = vec![];
                     let mut parameters = function_call.parameters.as_ref().unwrap_or(&EMPTY_VEC).iter().cloned();
                     let ret = super::#ident(
@@ -241,15 +242,56 @@ pub fn host_function(attr: TokenStream, item: TokenStream) -> TokenStream {
     }
 
     let ret = match &sig.output {
-        syn::ReturnType::Default => quote! { quote! { () } },
+        syn::ReturnType::Default => quote! { () },
         syn::ReturnType::Type(_, ty) => {
             quote! { #ty }
         }
     };
 
+    if sig.asyncness.is_some() {
+        // The call starts the host function and returns an id, the host then
+        // pushes an event when it completes and we can take the result
+        let output = quote! {
+
+            #(#attrs)*
+            #vis #sig {
+                use #crate_name::__private::alloc::vec;
+                use #crate_name::__private::hyperlight_common::flatbuffer_wrappers::function_types::ParameterValue;
+                use #crate_name::__private::hyperlight_guest::error::HyperlightGuestError;
+                use #crate_name::__private::hyperlight_guest::host_function_call::call_host_function;
+                let completion = #crate_name::__private::host_call::Completion::subscribe();
+                let id = call_host_function(
+                    #exported_name,
+                    Some(vec![
+                        #(<#args as #crate_name::__private::ty::ToFlatbufParameter>::to_value(#args_names)),*
+                    ]),
+                    <u64 as #crate_name::__private::ty::FromFlatbufReturn>::TYPE,
+                );
+                let id = <::core::result::Result<u64, HyperlightGuestError> as #crate_name::__private::ty::FromFlatbufReturn>::from_call(id);
+                let ret = match id {
+                    Ok(id) => {
+                        completion.wait(id).await;
+                        call_host_function(
+                            concat!(#exported_name, "/Result"),
+                            Some(vec![ParameterValue::ULong(id)]),
+                            <#ret as #crate_name::__private::ty::FromFlatbufReturn>::TYPE,
+                        )
+                    }
+                    Err(err) => Err(err),
+                };
+                <#ret as #crate_name::__private::ty::FromFlatbufReturn>::from_call(ret)
+            }
+
+        };
+
+        return output.into();
+    }
+
     let output = quote! {
 
-        #(#attrs)* #vis #sig {
+        #(#attrs)*
+        #[track_caller]
+        #vis #sig {
             use #crate_name::__private::alloc::vec;
             use #crate_name::__private::hyperlight_common::flatbuffer_wrappers::function_types::ReturnValue;
             use #crate_name::__private::hyperlight_guest::host_function_call::{get_host_return_value, call_host_function};
@@ -0,0 +1,21 @@
+use hl_guest_async::events::{self, Subscription};
+
+// Pushed by the host when an asynchronous call completes, with the id of the
+// call as a little endian u64
+const COMPLETED_EVENT: &str = "__HostCallCompleted";
+
+/// Waits for the completion of an asynchronous host function call.
+///
+/// It must be created before starting the call, so that the completion can't
+/// be taken from the host before we subscribed to it.
+pub struct Completion(Subscription);
+
+impl Completion {
+    pub fn subscribe() -> Self {
+        Completion(events::subscribe(COMPLETED_EVENT))
+    }
+
+    pub async fn wait(mut self, id: u64) {
+        while self.0.recv().await != id.to_le_bytes() {}
+    }
+}
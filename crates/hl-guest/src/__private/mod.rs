@@ -3,8 +3,23 @@ pub extern crate alloc;
 pub use linkme;
 pub use hyperlight_common;
 pub use hyperlight_guest;
+#[cfg(feature = "log")]
+pub use log;
 
 pub mod ty;
 
+/// Called by the guest functions before they run.
+#[cfg(feature = "async")]
+pub fn begin_call() {
+    hl_guest_async::begin_call();
+}
+
+// The guest functions call it whether the feature is enabled or not
+#[cfg(not(feature = "async"))]
+pub fn begin_call() {}
+
+#[cfg(feature = "async")]
+pub mod host_call;
+
 #[linkme::distributed_slice]
 pub static GUEST_FUNCTION_INIT: [fn()];
\ No newline at end of file
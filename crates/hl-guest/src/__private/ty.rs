@@ -12,6 +12,8 @@ use hyperlight_common::flatbuffer_wrappers::util::get_flatbuffer_result;
 use hyperlight_guest::error::HyperlightGuestError;
 use hyperlight_guest::host_function_call::get_host_return_value;
 
+use crate::panic::panic_with_report;
+
 pub trait ToFlatbufParameter: Sized {
     const TYPE: ParameterType;
     fn to_value(self) -> ParameterValue;
@@ -26,6 +28,7 @@ pub trait ToFlatbufReturn: Sized {
 
 pub trait FromFlatbufReturn: Sized {
     const TYPE: ReturnType;
+    #[track_caller]
     fn from_call(v: Result<(), HyperlightGuestError>) -> Self;
 }
 
@@ -124,17 +127,21 @@ impl_to_flatbuf_return! {
 
 impl<T: ToFlatbufReturn> FromFlatbufReturn for T {
     const TYPE: ReturnType = <T as ToFlatbufReturn>::TYPE;
+    #[track_caller]
     fn from_call(v: Result<(), HyperlightGuestError>) -> Self {
         if let Err(e) = v {
-            panic!("{}", e.message);
+            panic_with_report(e.message);
+        }
+        let ret = match get_host_return_value::<ReturnValue>() {
+            Ok(ret) => ret,
+            Err(e) => panic_with_report(e.message),
+        };
+        match T::from_value(ret) {
+            Ok(value) => value,
+            Err(e) => panic_with_report(e.message),
         }
-        let ret = get_host_return_value::<ReturnValue>().unwrap();
-        T::from_value(ret).unwrap_or_else(|e| {
-            panic!("{}", e.message);
-        })
     }
 }
-    
 
 impl<T: ToFlatbufReturn, E: From<HyperlightGuestError>> FromFlatbufReturn for Result<T, E> {
     const TYPE: ReturnType = <T as ToFlatbufReturn>::TYPE;
@@ -0,0 +1,134 @@
+//! The host calls that `hl-guest-async` is built on, exported as C symbols
+//! so that it doesn't depend on `hl-guest`.
+//!
+//! They are only linked with the `async` feature, which also makes
+//! `hl_guest::time::Instant` the one of `hl-guest-async`.
+
+use alloc::vec::Vec;
+use hyperlight_guest::error::HyperlightGuestError;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::time::Duration;
+
+use hl_guest_async::events::EVENTS_FD;
+
+use crate::host_function;
+
+#[host_function("GetMonotonicTime")]
+fn get_monotonic_time() -> u64;
+
+#[host_function("TryInput")]
+pub fn try_read(count: u64) -> Result<Vec<u8>, HyperlightGuestError>;
+
+#[host_function("PollReady")]
+pub fn poll_ready(interest: u32, timeout: u64) -> Result<u32, HyperlightGuestError>;
+
+#[host_function("NextEvent")]
+pub fn next_event() -> Result<Vec<u8>, HyperlightGuestError>;
+
+// What `PollReady` can wait for, as a bitmask
+const POLL_INPUT: u32 = 1;
+const POLL_EVENTS: u32 = 2;
+// Returned along with `POLL_INPUT` once stdin is closed and all its data was
+// read
+const POLL_CLOSED: u32 = 4;
+
+// Whether `PollReady` reported that stdin is closed, after which reading
+// nothing means the end of the input rather than "try again"
+static STDIN_CLOSED: AtomicBool = AtomicBool::new(false);
+
+#[repr(C)]
+#[allow(non_camel_case_types)]
+struct __timespec {
+    tv_sec: i64,
+    tv_nsec: i64,
+}
+
+#[no_mangle]
+extern "C" fn __monotonic() -> __timespec {
+    let ts = Duration::from_micros(get_monotonic_time());
+    __timespec {
+        tv_sec: ts.as_secs() as _,
+        tv_nsec: ts.subsec_nanos() as _,
+    }
+}
+
+#[no_mangle]
+extern "C" fn __try_read(fd: i32, buffer: *mut u8, len: usize) -> i32 {
+    if fd != 0 {
+        return -1;
+    }
+    let buffer = unsafe { core::slice::from_raw_parts_mut(buffer, len) };
+    let Ok(data) = try_read(len as u64) else {
+        return -1
+    };
+    let n = data.len().min(buffer.len());
+    if n == 0 && STDIN_CLOSED.load(Ordering::Relaxed) {
+        return 0;
+    }
+    if n == 0 {
+        return -2;
+    }
+    buffer[0..n].copy_from_slice(&data[0..n]);
+    if n > buffer.len() {
+        return -1;
+    }
+    n as _
+}
+
+fn interest(fd: i32) -> Option<u32> {
+    match fd {
+        0 => Some(POLL_INPUT),
+        EVENTS_FD => Some(POLL_EVENTS),
+        _ => None,
+    }
+}
+
+#[no_mangle]
+extern "C" fn __poll_read(fds: *mut i32, nfds: usize, timeout: __timespec) -> i32 {
+    let fds = unsafe { core::slice::from_raw_parts_mut(fds, nfds) };
+    let mut interests = 0;
+    for fd in fds.iter() {
+        let Some(interest) = interest(*fd) else {
+            return -1;
+        };
+        interests |= interest;
+    }
+    let timeout = Duration::new(timeout.tv_sec as _, timeout.tv_nsec as _);
+    // rounded up, as `PollReady` waits forever for a zero timeout
+    let timeout = timeout.as_nanos().div_ceil(1000).min(u64::MAX as _) as u64;
+    let Ok(ready) = poll_ready(interests, timeout) else {
+        return -1
+    };
+    if ready & POLL_CLOSED != 0 {
+        STDIN_CLOSED.store(true, Ordering::Relaxed);
+    }
+    let mut count = 0;
+    for fd in fds.iter_mut() {
+        if interest(*fd).is_some_and(|interest| ready & interest != 0) {
+            count += 1;
+        } else {
+            *fd = -1;
+        }
+    }
+    count
+}
+
+#[repr(C)]
+#[allow(non_camel_case_types)]
+struct __buffer {
+    ptr: *mut u8,
+    len: usize,
+    capacity: usize,
+}
+
+#[no_mangle]
+extern "C" fn __next_event() -> __buffer {
+    // an empty buffer when there's no event, or if the call failed
+    let event = next_event().unwrap_or_default();
+    let mut event = core::mem::ManuallyDrop::new(event);
+    __buffer {
+        ptr: event.as_mut_ptr(),
+        len: event.len(),
+        capacity: event.capacity(),
+    }
+}
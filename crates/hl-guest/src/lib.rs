@@ -22,6 +22,12 @@ use error::{HyperlightGuestError, ErrorCode};
 
 #[no_mangle]
 extern "C" fn hyperlight_main() {
+    #[cfg(feature = "log")]
+    log::init();
+
+    #[cfg(feature = "trace")]
+    trace::init();
+
     for registration in GUEST_FUNCTION_INIT {
         registration();
     }
@@ -37,7 +43,18 @@ fn guest_dispatch_function(
     ))
 }
 
+#[cfg(feature = "async")]
+mod host_impl;
 pub mod io;
+pub mod panic;
+pub mod time;
+pub mod tty;
+
+#[cfg(feature = "log")]
+pub mod log;
+
+#[cfg(feature = "trace")]
+mod trace;
 
 #[cfg(feature = "async")]
 pub mod asyncio;
\ No newline at end of file
@@ -0,0 +1,99 @@
+extern crate alloc;
+
+use alloc::string::{String, ToString as _};
+
+use ::log::{LevelFilter, Log, Metadata, Record};
+use hyperlight_guest::error::HyperlightGuestError;
+
+use crate::host_function;
+
+pub use ::log::Level;
+
+#[host_function("GuestLog")]
+fn host_log(
+    level: u32,
+    target: String,
+    module_path: String,
+    file: String,
+    line: u32,
+    message: String,
+) -> Result<(), HyperlightGuestError>;
+
+#[host_function("GetLogLevel")]
+fn host_log_level() -> Result<u32, HyperlightGuestError>;
+
+/// A [`Log`] implementation that forwards records to the `GuestLog` host function.
+///
+/// The global logger is owned by `hyperlight_guest`, which reports every record with
+/// the `hyperlight_guest` target. The logging macros in this crate (`hl_guest::info!`,
+/// etc.) use this logger instead, so that the host can filter on the guest's own
+/// targets and modules.
+pub struct HostLogger;
+
+impl Log for HostLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= ::log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let _ = host_log(
+            record.level() as u32,
+            record.target().to_string(),
+            record.module_path().unwrap_or_default().to_string(),
+            record.file().unwrap_or_default().to_string(),
+            record.line().unwrap_or_default(),
+            record.args().to_string(),
+        );
+    }
+
+    fn flush(&self) {}
+}
+
+// Use the host max level instead of the one `hyperlight_guest` derives from
+// `RUST_LOG`, so that records enabled for any target reach the host filter.
+pub(crate) fn init() {
+    if let Ok(level) = host_log_level() {
+        let level = LevelFilter::iter()
+            .nth(level as usize)
+            .unwrap_or(LevelFilter::Trace);
+        ::log::set_max_level(level);
+    }
+}
+
+#[macro_export]
+macro_rules! error {
+    ($($arg:tt)+) => {
+        $crate::__private::log::error!(logger: $crate::log::HostLogger, $($arg)+)
+    };
+}
+
+#[macro_export]
+macro_rules! warn {
+    ($($arg:tt)+) => {
+        $crate::__private::log::warn!(logger: $crate::log::HostLogger, $($arg)+)
+    };
+}
+
+#[macro_export]
+macro_rules! info {
+    ($($arg:tt)+) => {
+        $crate::__private::log::info!(logger: $crate::log::HostLogger, $($arg)+)
+    };
+}
+
+#[macro_export]
+macro_rules! debug {
+    ($($arg:tt)+) => {
+        $crate::__private::log::debug!(logger: $crate::log::HostLogger, $($arg)+)
+    };
+}
+
+#[macro_export]
+macro_rules! trace {
+    ($($arg:tt)+) => {
+        $crate::__private::log::trace!(logger: $crate::log::HostLogger, $($arg)+)
+    };
+}
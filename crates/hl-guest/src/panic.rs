@@ -0,0 +1,95 @@
+extern crate alloc;
+
+use alloc::string::{String, ToString as _};
+use alloc::vec::Vec;
+
+use core::fmt::Display;
+use core::panic::Location;
+
+use hyperlight_guest::error::HyperlightGuestError;
+
+use crate::host_function;
+
+#[host_function("GuestPanic")]
+fn report_panic(
+    message: String,
+    file: String,
+    line: u32,
+    backtrace: Vec<u8>,
+) -> Result<(), HyperlightGuestError>;
+
+/// Reports a panic to the host and then panics.
+///
+/// The `#[panic_handler]` is owned by `hyperlight_guest`, which aborts the
+/// sandbox with the formatted panic message and location, that the host turns
+/// into a report. Panics raised through this function are first sent to the
+/// `GuestPanic` host function, together with the caller location and, with the
+/// `backtrace` feature, the return addresses of the stack frames, so that the
+/// host can print a symbolized backtrace too.
+#[track_caller]
+pub fn panic_with_report(message: impl Display) -> ! {
+    let location = Location::caller();
+    report(&message, location);
+    panic!("{message}");
+}
+
+/// Sends a panic report to the host without panicking.
+pub fn report(message: &impl Display, location: &Location) {
+    // if the host doesn't know about `GuestPanic` there's nothing else we can
+    // do, the panic message still reaches the host through the abort.
+    let _ = report_panic(
+        message.to_string(),
+        location.file().to_string(),
+        location.line(),
+        backtrace(),
+    );
+}
+
+#[cfg(feature = "backtrace")]
+const MAX_FRAMES: usize = 64;
+
+// The backtrace is sent as a list of little endian u64. The first entry is the
+// runtime address of `hyperlight_main`, which the host uses to compute the load
+// address of the guest binary. The rest are the return addresses of each frame.
+#[cfg(feature = "backtrace")]
+#[inline(always)]
+fn backtrace() -> Vec<u8> {
+    let mut frames = alloc::vec![crate::hyperlight_main as *const () as usize];
+
+    let mut fp: usize;
+    // SAFETY: reading rbp has no side effects
+    unsafe {
+        core::arch::asm!("mov {}, rbp", out(reg) fp, options(nomem, nostack, preserves_flags));
+    }
+
+    // This relies on the guest being built with frame pointers, e.g.,
+    // `-Cforce-frame-pointers=yes`, otherwise rbp is a general purpose register.
+    let aligned = |fp: usize| fp & (core::mem::align_of::<usize>() - 1) == 0;
+    while fp != 0 && aligned(fp) && frames.len() <= MAX_FRAMES {
+        // SAFETY: with frame pointers, rbp points to the saved rbp of the caller,
+        // followed by the return address
+        let (next, ret) = unsafe {
+            let fp = fp as *const usize;
+            (*fp, *fp.add(1))
+        };
+        if ret == 0 {
+            break;
+        }
+        frames.push(ret);
+        // the stack grows downwards, so the caller frame is always at a higher address
+        if next <= fp {
+            break;
+        }
+        fp = next;
+    }
+
+    frames
+        .into_iter()
+        .flat_map(|addr| (addr as u64).to_le_bytes())
+        .collect()
+}
+
+#[cfg(not(feature = "backtrace"))]
+fn backtrace() -> Vec<u8> {
+    Vec::new()
+}
@@ -0,0 +1,194 @@
+//! Temporal quantification, mirroring `std::time`.
+//!
+//! [`Instant`] uses the monotonic clock of the host (`GetMonotonicTime`), and
+//! [`SystemTime`] its wall clock (`GetTime`). With the `async` feature,
+//! [`Instant`] is the one of the async runtime, so it can be used for its
+//! timers.
+
+use core::fmt;
+use core::ops::{Add, AddAssign, Sub, SubAssign};
+
+pub use core::time::Duration;
+#[cfg(feature = "async")]
+pub use hl_guest_async::time::Instant;
+#[cfg(not(feature = "async"))]
+pub use instant::Instant;
+
+use crate::host_function;
+
+#[host_function("GetTime")]
+fn host_get_time() -> u64;
+
+#[cfg(not(feature = "async"))]
+mod instant {
+    use core::ops::{Add, AddAssign, Sub, SubAssign};
+    use core::time::Duration;
+
+    use crate::host_function;
+
+    #[host_function("GetMonotonicTime")]
+    fn host_get_monotonic_time() -> u64;
+
+    /// A measurement of the host's monotonic clock, unaffected by
+    /// adjustments of the host's wall clock.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct Instant(Duration);
+
+    impl Instant {
+        pub fn now() -> Instant {
+            Instant(Duration::from_micros(host_get_monotonic_time()))
+        }
+
+        /// Returns the amount of time elapsed from another instant to this
+        /// one, or zero if that instant is later than this one.
+        pub fn duration_since(&self, earlier: Instant) -> Duration {
+            self.saturating_duration_since(earlier)
+        }
+
+        pub fn checked_duration_since(&self, earlier: Instant) -> Option<Duration> {
+            self.0.checked_sub(earlier.0)
+        }
+
+        pub fn saturating_duration_since(&self, earlier: Instant) -> Duration {
+            self.0.saturating_sub(earlier.0)
+        }
+
+        pub fn elapsed(&self) -> Duration {
+            Instant::now().duration_since(*self)
+        }
+
+        pub fn checked_add(&self, duration: Duration) -> Option<Instant> {
+            self.0.checked_add(duration).map(Instant)
+        }
+
+        pub fn checked_sub(&self, duration: Duration) -> Option<Instant> {
+            self.0.checked_sub(duration).map(Instant)
+        }
+    }
+
+    impl Add<Duration> for Instant {
+        type Output = Instant;
+
+        fn add(self, other: Duration) -> Instant {
+            self.checked_add(other)
+                .expect("overflow when adding duration to instant")
+        }
+    }
+
+    impl AddAssign<Duration> for Instant {
+        fn add_assign(&mut self, other: Duration) {
+            *self = *self + other;
+        }
+    }
+
+    impl Sub<Duration> for Instant {
+        type Output = Instant;
+
+        fn sub(self, other: Duration) -> Instant {
+            self.checked_sub(other)
+                .expect("overflow when subtracting duration from instant")
+        }
+    }
+
+    impl SubAssign<Duration> for Instant {
+        fn sub_assign(&mut self, other: Duration) {
+            *self = *self - other;
+        }
+    }
+
+    impl Sub<Instant> for Instant {
+        type Output = Duration;
+
+        fn sub(self, other: Instant) -> Duration {
+            self.duration_since(other)
+        }
+    }
+}
+
+/// A measurement of the host's wall clock.
+///
+/// Unlike [`Instant`], it can go backwards if the host's clock is adjusted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SystemTime(Duration);
+
+/// The Unix epoch, 1970-01-01 00:00:00 UTC.
+pub const UNIX_EPOCH: SystemTime = SystemTime::UNIX_EPOCH;
+
+impl SystemTime {
+    pub const UNIX_EPOCH: SystemTime = SystemTime(Duration::ZERO);
+
+    pub fn now() -> SystemTime {
+        SystemTime(Duration::from_micros(host_get_time()))
+    }
+
+    /// Returns the amount of time elapsed from an earlier point in time, or
+    /// an error with how much later it is.
+    pub fn duration_since(&self, earlier: SystemTime) -> Result<Duration, SystemTimeError> {
+        match self.0.checked_sub(earlier.0) {
+            Some(duration) => Ok(duration),
+            None => Err(SystemTimeError(earlier.0 - self.0)),
+        }
+    }
+
+    pub fn elapsed(&self) -> Result<Duration, SystemTimeError> {
+        SystemTime::now().duration_since(*self)
+    }
+
+    pub fn checked_add(&self, duration: Duration) -> Option<SystemTime> {
+        self.0.checked_add(duration).map(SystemTime)
+    }
+
+    pub fn checked_sub(&self, duration: Duration) -> Option<SystemTime> {
+        self.0.checked_sub(duration).map(SystemTime)
+    }
+}
+
+impl Add<Duration> for SystemTime {
+    type Output = SystemTime;
+
+    fn add(self, dur: Duration) -> SystemTime {
+        self.checked_add(dur)
+            .expect("overflow when adding duration to system time")
+    }
+}
+
+impl AddAssign<Duration> for SystemTime {
+    fn add_assign(&mut self, other: Duration) {
+        *self = *self + other;
+    }
+}
+
+impl Sub<Duration> for SystemTime {
+    type Output = SystemTime;
+
+    fn sub(self, dur: Duration) -> SystemTime {
+        self.checked_sub(dur)
+            .expect("overflow when subtracting duration from system time")
+    }
+}
+
+impl SubAssign<Duration> for SystemTime {
+    fn sub_assign(&mut self, other: Duration) {
+        *self = *self - other;
+    }
+}
+
+/// The error returned by [`SystemTime::duration_since`] when the second time
+/// is later than the first one.
+#[derive(Clone, Debug)]
+pub struct SystemTimeError(Duration);
+
+impl SystemTimeError {
+    /// How much later the second time is.
+    pub fn duration(&self) -> Duration {
+        self.0
+    }
+}
+
+impl fmt::Display for SystemTimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("second time provided was later than self")
+    }
+}
+
+impl core::error::Error for SystemTimeError {}
@@ -0,0 +1,211 @@
+extern crate alloc;
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use core::fmt::{Debug, Write as _};
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use hyperlight_guest::error::HyperlightGuestError;
+use spin::Mutex;
+use tracing_core::field::{Field, Visit};
+use tracing_core::span::{Attributes, Id, Record};
+use tracing_core::{dispatcher, Dispatch, Event, Level, Metadata, Subscriber};
+
+use crate::host_function;
+
+#[host_function("TraceEnabled")]
+fn host_trace_enabled() -> Result<bool, HyperlightGuestError>;
+
+#[host_function("TraceSpanNew")]
+fn host_span_new(
+    id: u64,
+    parent: u64,
+    name: String,
+    target: String,
+    fields: String,
+) -> Result<(), HyperlightGuestError>;
+
+#[host_function("TraceSpanRecord")]
+fn host_span_record(id: u64, fields: String) -> Result<(), HyperlightGuestError>;
+
+#[host_function("TraceSpanEnter")]
+fn host_span_enter(id: u64) -> Result<(), HyperlightGuestError>;
+
+#[host_function("TraceSpanExit")]
+fn host_span_exit(id: u64) -> Result<(), HyperlightGuestError>;
+
+#[host_function("TraceSpanClose")]
+fn host_span_close(id: u64) -> Result<(), HyperlightGuestError>;
+
+#[host_function("TraceEvent")]
+fn host_event(
+    parent: u64,
+    level: u32,
+    target: String,
+    fields: String,
+) -> Result<(), HyperlightGuestError>;
+
+/// A [`Subscriber`] that forwards spans and events to the host.
+///
+/// Span ids are allocated by the guest, the host maps them to its own spans.
+/// Spans without an explicit parent are attached to the innermost entered span,
+/// or to the host span around the guest function call if there's none.
+struct HostSubscriber {
+    next_id: AtomicU64,
+    refs: Mutex<BTreeMap<u64, usize>>,
+    stack: Mutex<Vec<u64>>,
+}
+
+impl HostSubscriber {
+    fn new() -> Self {
+        Self {
+            next_id: AtomicU64::new(1),
+            refs: Mutex::default(),
+            stack: Mutex::default(),
+        }
+    }
+
+    fn current(&self) -> u64 {
+        self.stack.lock().last().copied().unwrap_or(0)
+    }
+}
+
+impl Subscriber for HostSubscriber {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        // filtering is done by the host
+        true
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let parent = if span.is_root() {
+            0
+        } else if let Some(parent) = span.parent() {
+            parent.into_u64()
+        } else {
+            self.current()
+        };
+        self.refs.lock().insert(id, 1);
+
+        let metadata = span.metadata();
+        let mut fields = Fields::default();
+        span.record(&mut fields);
+        let _ = host_span_new(
+            id,
+            parent,
+            metadata.name().into(),
+            metadata.target().into(),
+            fields.0,
+        );
+
+        Id::from_u64(id)
+    }
+
+    fn record(&self, span: &Id, values: &Record<'_>) {
+        let mut fields = Fields::default();
+        values.record(&mut fields);
+        let _ = host_span_record(span.into_u64(), fields.0);
+    }
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let parent = if event.is_root() {
+            0
+        } else if let Some(parent) = event.parent() {
+            parent.into_u64()
+        } else {
+            self.current()
+        };
+
+        let metadata = event.metadata();
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        let _ = host_event(
+            parent,
+            level_to_u32(metadata.level()),
+            metadata.target().into(),
+            fields.0,
+        );
+    }
+
+    fn enter(&self, span: &Id) {
+        self.stack.lock().push(span.into_u64());
+        let _ = host_span_enter(span.into_u64());
+    }
+
+    fn exit(&self, span: &Id) {
+        let mut stack = self.stack.lock();
+        if let Some(pos) = stack.iter().rposition(|id| *id == span.into_u64()) {
+            stack.remove(pos);
+        }
+        drop(stack);
+        let _ = host_span_exit(span.into_u64());
+    }
+
+    fn clone_span(&self, span: &Id) -> Id {
+        if let Some(refs) = self.refs.lock().get_mut(&span.into_u64()) {
+            *refs += 1;
+        }
+        span.clone()
+    }
+
+    fn try_close(&self, span: Id) -> bool {
+        let mut refs = self.refs.lock();
+        let Some(count) = refs.get_mut(&span.into_u64()) else {
+            return false;
+        };
+        *count -= 1;
+        if *count > 0 {
+            return false;
+        }
+        refs.remove(&span.into_u64());
+        drop(refs);
+        let _ = host_span_close(span.into_u64());
+        true
+    }
+}
+
+// Same numbering as `log::Level`, which the host also uses for `GuestLog`.
+fn level_to_u32(level: &Level) -> u32 {
+    match *level {
+        Level::ERROR => 1,
+        Level::WARN => 2,
+        Level::INFO => 3,
+        Level::DEBUG => 4,
+        Level::TRACE => 5,
+    }
+}
+
+// Formats the fields as `message key1=value1 key2=value2`
+#[derive(Default)]
+struct Fields(String);
+
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        if !self.0.is_empty() {
+            self.0.push(' ');
+        }
+        if field.name() == "message" {
+            let _ = write!(self.0, "{value:?}");
+        } else {
+            let _ = write!(self.0, "{}={value:?}", field.name());
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.record_debug(field, &format_args!("{value}"));
+        } else {
+            self.record_debug(field, &value);
+        }
+    }
+}
+
+pub(crate) fn init() {
+    if let Ok(true) = host_trace_enabled() {
+        let _ = dispatcher::set_global_default(Dispatch::new(HostSubscriber::new()));
+    }
+}
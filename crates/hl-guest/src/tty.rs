@@ -0,0 +1,133 @@
+//! The terminal of the host, to build interactive guests.
+//!
+//! The host restores the terminal's original mode when it exits, so a guest
+//! that crashes in raw mode doesn't leave the terminal unusable.
+
+use alloc::string::ToString as _;
+use alloc::vec::Vec;
+
+use crate::error::{ErrorCode, HyperlightGuestError};
+use crate::host_function;
+
+#[host_function("IsTerminal")]
+fn host_is_terminal(fd: i32) -> Result<bool, HyperlightGuestError>;
+
+#[host_function("TerminalSize")]
+fn host_terminal_size() -> Result<Vec<u8>, HyperlightGuestError>;
+
+#[host_function("SetRawMode")]
+fn host_set_raw_mode(enabled: bool) -> Result<bool, HyperlightGuestError>;
+
+/// Tells whether a stream of the host refers to a terminal, like
+/// `std::io::IsTerminal`.
+pub trait IsTerminal {
+    fn is_terminal(&self) -> bool;
+}
+
+impl IsTerminal for crate::io::Stdout {
+    fn is_terminal(&self) -> bool {
+        host_is_terminal(1).unwrap_or(false)
+    }
+}
+
+#[cfg(feature = "async")]
+impl IsTerminal for crate::asyncio::io::Stdin {
+    fn is_terminal(&self) -> bool {
+        host_is_terminal(0).unwrap_or(false)
+    }
+}
+
+/// The size of the terminal, in characters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WindowSize {
+    pub columns: u16,
+    pub rows: u16,
+}
+
+impl WindowSize {
+    // The columns and rows as little endian u16s, or nothing without a
+    // terminal
+    fn decode(data: &[u8]) -> Option<Self> {
+        let [c0, c1, r0, r1] = *data else {
+            return None;
+        };
+        Some(WindowSize {
+            columns: u16::from_le_bytes([c0, c1]),
+            rows: u16::from_le_bytes([r0, r1]),
+        })
+    }
+}
+
+/// Returns the size of the terminal, or `None` if the host doesn't run in
+/// one.
+pub fn window_size() -> Option<WindowSize> {
+    WindowSize::decode(&host_terminal_size().ok()?)
+}
+
+/// Enables or disables raw mode, where stdin is read byte by byte as keys
+/// are pressed, without echoing them.
+///
+/// In raw mode, keys like Ctrl-C are read as bytes instead of signaling the
+/// host, so the guest must handle them.
+pub fn set_raw_mode(enabled: bool) -> Result<(), HyperlightGuestError> {
+    if !host_set_raw_mode(enabled)? {
+        return Err(HyperlightGuestError::new(
+            ErrorCode::GuestError,
+            "stdin is not a terminal".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+pub use resize::{resize_events, ResizeEvents};
+
+#[cfg(feature = "async")]
+mod resize {
+    use core::pin::Pin;
+    use core::task::{ready, Context, Poll};
+
+    use hl_guest_async::events::{self, Subscription};
+    use hl_guest_async::Stream;
+
+    use super::WindowSize;
+
+    // Pushed by the host when the terminal is resized, with its new size
+    // encoded like the result of `TerminalSize`
+    const RESIZE_EVENT: &str = "__Resize";
+
+    /// Returns a stream of the new sizes of the terminal, every time it's
+    /// resized.
+    pub fn resize_events() -> ResizeEvents {
+        ResizeEvents(events::subscribe(RESIZE_EVENT))
+    }
+
+    /// A stream of the new sizes of the terminal, returned by
+    /// [`resize_events`].
+    pub struct ResizeEvents(Subscription);
+
+    impl ResizeEvents {
+        /// Waits for the next resize, and returns the new size.
+        pub async fn recv(&mut self) -> WindowSize {
+            loop {
+                if let Some(size) = WindowSize::decode(&self.0.recv().await) {
+                    return size;
+                }
+            }
+        }
+    }
+
+    impl Stream for ResizeEvents {
+        type Item = WindowSize;
+
+        fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<WindowSize>> {
+            let this = self.get_mut();
+            loop {
+                let data = ready!(this.0.poll_recv(cx));
+                if let Some(size) = WindowSize::decode(&data) {
+                    return Poll::Ready(Some(size));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use hyperlight_host::{HyperlightError, Result};
+
+use crate::events::{Event, EventQueue};
+
+/// The event pushed to the guest when an asynchronous call completes, with
+/// the id of the call as a little endian u64.
+pub const COMPLETED_EVENT: &str = "__HostCallCompleted";
+
+/// Runs the host functions that guests call asynchronously, each call on its
+/// own thread, so that the guest keeps running while the host works.
+///
+/// Such a function `Name` is registered as two host functions:
+/// * `Name` starts the call and returns its id.
+/// * `Name/Result` takes the id and returns the result, once the guest
+///   received the [`COMPLETED_EVENT`] of the call.
+#[derive(Clone)]
+pub struct AsyncCalls {
+    events: EventQueue,
+    next_id: Arc<AtomicU64>,
+}
+
+/// The results of the completed calls to a host function, by id.
+pub(crate) type Results<R> = Arc<Mutex<HashMap<u64, Result<R>>>>;
+
+impl AsyncCalls {
+    pub fn new(events: EventQueue) -> Self {
+        AsyncCalls {
+            events,
+            next_id: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Runs `f` on a new thread, and returns the id of the call.
+    pub(crate) fn start<R: Send + 'static>(
+        &self,
+        results: &Results<R>,
+        f: impl FnOnce() -> Result<R> + Send + 'static,
+    ) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let results = results.clone();
+        let events = self.events.clone();
+        // the virtual clock doesn't advance while the completion is coming
+        events.expect();
+        std::thread::spawn(move || {
+            let result = f();
+            results.lock().unwrap().insert(id, result);
+            events.push_expected(Event::new(COMPLETED_EVENT, id.to_le_bytes()));
+        });
+        id
+    }
+
+    /// Takes the result of the call `id` to `name`.
+    pub(crate) fn result<R>(results: &Results<R>, name: &str, id: u64) -> Result<R> {
+        results.lock().unwrap().remove(&id).unwrap_or_else(|| {
+            Err(HyperlightError::Error(format!(
+                "The call {id} to {name} didn't complete"
+            )))
+        })
+    }
+}
@@ -0,0 +1,181 @@
+use std::fmt;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use crate::events::{Event, EventQueue};
+
+/// The event pushed to the guest to cancel its `CancellationToken::host()`.
+pub const CANCEL_EVENT: &str = "__Cancel";
+
+/// Why a guest call was cancelled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CancelReason {
+    /// [`Canceller::cancel`] was called, e.g., on Ctrl-C.
+    Cancelled,
+    /// The call ran longer than its timeout.
+    TimedOut,
+}
+
+impl fmt::Display for CancelReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CancelReason::Cancelled => f.write_str("cancelled"),
+            CancelReason::TimedOut => f.write_str("timed out"),
+        }
+    }
+}
+
+type Kill = Box<dyn Fn(CancelReason) + Send + Sync>;
+
+/// Cancels the guest calls in progress, from any thread.
+///
+/// The guest is first asked to stop with a [`CANCEL_EVENT`], and is only
+/// killed if the call doesn't return within a grace period. The timeouts and
+/// grace periods are enforced by a single watchdog thread, that lives as long
+/// as the process.
+#[derive(Clone)]
+pub struct Canceller {
+    calls: Arc<Calls>,
+}
+
+struct Calls {
+    state: Mutex<State>,
+    // notified when a call starts, returns or is cancelled, so that the
+    // watchdog looks at the deadlines again
+    changed: Condvar,
+    events: EventQueue,
+    grace: Duration,
+    kill: Kill,
+}
+
+#[derive(Default)]
+struct State {
+    // the call in progress
+    running: Option<Call>,
+}
+
+struct Call {
+    // cancelled when reached
+    timeout: Option<Instant>,
+    // why the call was cancelled
+    cancelled: Option<CancelReason>,
+    // killed when reached, unset once it was
+    kill_at: Option<Instant>,
+}
+
+impl Canceller {
+    /// Creates a canceller that calls `kill` when a cancelled call doesn't
+    /// return within `grace`, e.g., to exit the process.
+    pub fn new(
+        events: EventQueue,
+        grace: Duration,
+        kill: impl Fn(CancelReason) + Send + Sync + 'static,
+    ) -> Self {
+        let calls = Arc::new(Calls {
+            state: Mutex::default(),
+            changed: Condvar::new(),
+            events,
+            grace,
+            kill: Box::new(kill),
+        });
+        let watchdog = calls.clone();
+        std::thread::spawn(move || watchdog.watch());
+        Canceller { calls }
+    }
+
+    /// Runs `f`, a call to the guest that [`Canceller::cancel`] can cancel,
+    /// and that is cancelled if it runs longer than `timeout`.
+    ///
+    /// Returns the result of `f`, and why the call was cancelled, if it was.
+    pub fn call<R>(
+        &self,
+        timeout: Option<Duration>,
+        f: impl FnOnce() -> R,
+    ) -> (R, Option<CancelReason>) {
+        self.calls.state.lock().unwrap().running = Some(Call {
+            timeout: timeout.map(|timeout| Instant::now() + timeout),
+            cancelled: None,
+            kill_at: None,
+        });
+        self.calls.changed.notify_all();
+        let result = f();
+        let call = self.calls.state.lock().unwrap().running.take();
+        self.calls.changed.notify_all();
+        // the guest may have returned before taking the cancel event, which
+        // would otherwise cancel the next call
+        self.calls.events.remove(CANCEL_EVENT);
+        (result, call.and_then(|call| call.cancelled))
+    }
+
+    /// Asks the guest call in progress to stop, or kills it right away if it
+    /// was already cancelled.
+    ///
+    /// Returns false if no call is in progress.
+    pub fn cancel(&self) -> bool {
+        let mut state = self.calls.state.lock().unwrap();
+        let Some(call) = &mut state.running else {
+            return false;
+        };
+        if call.cancelled.is_some() {
+            drop(state);
+            (self.calls.kill)(CancelReason::Cancelled);
+            return true;
+        }
+        self.calls.cancel(call, CancelReason::Cancelled);
+        true
+    }
+}
+
+impl Calls {
+    // Asks the guest to stop the call, and starts its grace period
+    fn cancel(&self, call: &mut Call, reason: CancelReason) {
+        call.cancelled = Some(reason);
+        call.kill_at = Some(Instant::now() + self.grace);
+        self.events.push(Event::new(CANCEL_EVENT, Vec::new()));
+        self.changed.notify_all();
+    }
+
+    // Cancels the calls that time out, and kills the ones that don't return
+    // within their grace period
+    fn watch(&self) {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            let now = Instant::now();
+            let deadline = match &mut state.running {
+                None => None,
+                Some(call) => match (call.cancelled, call.kill_at) {
+                    (Some(reason), Some(kill_at)) if kill_at <= now => {
+                        call.kill_at = None;
+                        drop(state);
+                        (self.kill)(reason);
+                        state = self.state.lock().unwrap();
+                        continue;
+                    }
+                    (Some(_), kill_at) => kill_at,
+                    (None, _) => match call.timeout {
+                        Some(timeout) if timeout <= now => {
+                            self.cancel(call, CancelReason::TimedOut);
+                            continue;
+                        }
+                        timeout => timeout,
+                    },
+                },
+            };
+            state = self.wait(state, deadline);
+        }
+    }
+
+    fn wait<'a>(
+        &self,
+        state: MutexGuard<'a, State>,
+        deadline: Option<Instant>,
+    ) -> MutexGuard<'a, State> {
+        match deadline {
+            None => self.changed.wait(state).unwrap(),
+            Some(deadline) => {
+                let timeout = deadline.saturating_duration_since(Instant::now());
+                self.changed.wait_timeout(state, timeout).unwrap().0
+            }
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// The clock behind the `GetTime`, `GetMonotonicTime`, `Sleep` and
+/// `PollReady` host functions.
+pub enum Clock {
+    /// The system clocks, with the monotonic time measured from `start`.
+    Real { start: Instant },
+    /// A clock that starts at the system time and only advances when the
+    /// guest sleeps, or waits for input with a timeout and there's no input
+    /// coming, so that timers fire instantly.
+    Virtual {
+        start: Duration,
+        elapsed: Mutex<Duration>,
+    },
+}
+
+impl Clock {
+    pub fn new(virtual_time: bool) -> Self {
+        if virtual_time {
+            Clock::Virtual {
+                start: system_time(),
+                elapsed: Mutex::default(),
+            }
+        } else {
+            Clock::Real {
+                start: Instant::now(),
+            }
+        }
+    }
+
+    /// The time since the Unix epoch.
+    pub fn now(&self) -> Duration {
+        match self {
+            Clock::Real { .. } => system_time(),
+            Clock::Virtual { start, elapsed } => *start + *elapsed.lock().unwrap(),
+        }
+    }
+
+    /// The time since the clock was created, which never goes backwards.
+    pub fn monotonic(&self) -> Duration {
+        match self {
+            Clock::Real { start } => start.elapsed(),
+            Clock::Virtual { elapsed, .. } => *elapsed.lock().unwrap(),
+        }
+    }
+
+    pub fn sleep(&self, duration: Duration) {
+        match self {
+            Clock::Real { .. } => std::thread::sleep(duration),
+            Clock::Virtual { elapsed, .. } => *elapsed.lock().unwrap() += duration,
+        }
+    }
+
+    pub fn is_virtual(&self) -> bool {
+        matches!(self, Clock::Virtual { .. })
+    }
+}
+
+fn system_time() -> Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+}
@@ -0,0 +1,106 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// An event pushed by the host to the guest, that the guest receives by
+/// subscribing to its name.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Event {
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+impl Event {
+    pub fn new(name: impl Into<String>, data: impl Into<Vec<u8>>) -> Self {
+        Event {
+            name: name.into(),
+            data: data.into(),
+        }
+    }
+
+    /// Encodes the event as returned by the `NextEvent` host function: the
+    /// length of the name as a little endian u32, the name, and the data.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buffer = Vec::with_capacity(4 + self.name.len() + self.data.len());
+        buffer.extend_from_slice(&(self.name.len() as u32).to_le_bytes());
+        buffer.extend_from_slice(self.name.as_bytes());
+        buffer.extend_from_slice(&self.data);
+        buffer
+    }
+}
+
+type Listener = Box<dyn Fn() + Send + Sync>;
+
+/// The events waiting to be received by the guest.
+///
+/// Clones share the same queue, so events can be pushed from any thread
+/// while the guest is running.
+#[derive(Clone, Default)]
+pub struct EventQueue(Arc<Inner>);
+
+#[derive(Default)]
+struct Inner {
+    events: Mutex<VecDeque<Event>>,
+    // the events announced with `expect` and not pushed yet, only changed
+    // while holding the `events` lock
+    expected: AtomicUsize,
+    listeners: Mutex<Vec<Listener>>,
+}
+
+impl EventQueue {
+    pub fn push(&self, event: Event) {
+        self.0.events.lock().unwrap().push_back(event);
+        self.notify();
+    }
+
+    /// Announces an event that will be pushed later with `push_expected`.
+    pub fn expect(&self) {
+        let _events = self.0.events.lock().unwrap();
+        self.0.expected.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Pushes an event announced with `expect`.
+    pub fn push_expected(&self, event: Event) {
+        let mut events = self.0.events.lock().unwrap();
+        events.push_back(event);
+        self.0.expected.fetch_sub(1, Ordering::SeqCst);
+        drop(events);
+        self.notify();
+    }
+
+    fn notify(&self) {
+        for listener in self.0.listeners.lock().unwrap().iter() {
+            listener();
+        }
+    }
+
+    pub fn pop(&self) -> Option<Event> {
+        self.0.events.lock().unwrap().pop_front()
+    }
+
+    /// Removes the events named `name` that the guest didn't receive yet.
+    pub fn remove(&self, name: &str) {
+        self.0
+            .events
+            .lock()
+            .unwrap()
+            .retain(|event| event.name != name);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.events.lock().unwrap().is_empty()
+    }
+
+    /// Whether there are no events, and none announced with `expect` are
+    /// coming.
+    pub fn is_idle(&self) -> bool {
+        let events = self.0.events.lock().unwrap();
+        events.is_empty() && self.0.expected.load(Ordering::SeqCst) == 0
+    }
+
+    /// Calls `listener` every time an event is pushed, e.g., to wake up a
+    /// thread waiting for events.
+    pub fn on_push(&self, listener: impl Fn() + Send + Sync + 'static) {
+        self.0.listeners.lock().unwrap().push(Box::new(listener));
+    }
+}
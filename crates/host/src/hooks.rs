@@ -0,0 +1,181 @@
+use std::sync::{Arc, Mutex};
+
+use hyperlight_host::func::{
+    HostFunction0, HostFunction1, HostFunction2, HostFunction3, HostFunction4, HostFunction5,
+    HostFunction6, ParameterValue, ReturnValue, SupportedParameterType, SupportedReturnType,
+};
+use hyperlight_host::{HyperlightError, Result, UninitializedSandbox};
+
+use crate::async_calls::{AsyncCalls, Results};
+
+/// A hook that runs around every host function call.
+///
+/// `next` runs the rest of the hooks and the host function itself. A hook can
+/// inspect the arguments and the returned value, or not call `next` at all and
+/// return a different value.
+pub trait Hook: Send + Sync {
+    fn call(
+        &self,
+        name: &str,
+        args: &[ParameterValue],
+        next: &mut dyn FnMut() -> Result<ReturnValue>,
+    ) -> Result<ReturnValue>;
+}
+
+/// A host function parameter type.
+pub trait Param: SupportedParameterType<Self> + Clone + Send + 'static {
+    fn to_value(&self) -> ParameterValue;
+}
+
+/// A host function return type.
+pub trait Return: SupportedReturnType<Self> + Sized + Send + 'static {
+    fn to_value(self) -> ReturnValue;
+    fn from_value(value: ReturnValue) -> Result<Self>;
+}
+
+macro_rules! impl_param_return {
+    ($($type:ty => $enum:ident;)+) => {
+        $(impl Param for $type {
+            fn to_value(&self) -> ParameterValue {
+                ParameterValue::$enum(self.clone())
+            }
+        }
+
+        impl Return for $type {
+            fn to_value(self) -> ReturnValue {
+                ReturnValue::$enum(self)
+            }
+
+            fn from_value(value: ReturnValue) -> Result<Self> {
+                match value {
+                    ReturnValue::$enum(value) => Ok(value),
+                    value => Err(HyperlightError::Error(format!(
+                        "Expected return type {}, but got {value:?}",
+                        std::any::type_name::<$type>(),
+                    ))),
+                }
+            }
+        })+
+    };
+}
+
+impl_param_return! {
+    i32 => Int;
+    u32 => UInt;
+    i64 => Long;
+    u64 => ULong;
+    f32 => Float;
+    f64 => Double;
+    bool => Bool;
+    String => String;
+    Vec<u8> => VecBytes;
+}
+
+impl Return for () {
+    fn to_value(self) -> ReturnValue {
+        ReturnValue::Void
+    }
+
+    fn from_value(value: ReturnValue) -> Result<Self> {
+        match value {
+            ReturnValue::Void => Ok(()),
+            value => Err(HyperlightError::Error(format!(
+                "Expected return type (), but got {value:?}"
+            ))),
+        }
+    }
+}
+
+/// A chain of hooks that can wrap and register host functions.
+#[derive(Clone, Default)]
+pub struct Hooks(Vec<Arc<dyn Hook>>);
+
+impl Hooks {
+    /// Adds a hook. Hooks run in the order they were added.
+    pub fn push(&mut self, hook: Arc<dyn Hook>) {
+        self.0.push(hook);
+    }
+
+    fn call(
+        hooks: &[Arc<dyn Hook>],
+        name: &str,
+        args: &[ParameterValue],
+        f: &mut dyn FnMut() -> Result<ReturnValue>,
+    ) -> Result<ReturnValue> {
+        match hooks.split_first() {
+            None => f(),
+            Some((hook, rest)) => hook.call(name, args, &mut || Self::call(rest, name, args, f)),
+        }
+    }
+}
+
+macro_rules! impl_wrap_register {
+    ($wrap:ident, $register:ident, $register_async:ident, $trait:ident $(, $p:ident: $P:ident)*) => {
+        impl Hooks {
+            /// Wraps a host function so that it runs through the hooks.
+            pub fn $wrap<$($P: Param,)* R: Return>(
+                &self,
+                name: &'static str,
+                mut f: impl FnMut($($P),*) -> Result<R> + Send + 'static,
+            ) -> impl FnMut($($P),*) -> Result<R> + Send + 'static {
+                let hooks = self.clone();
+                move |$($p: $P),*| {
+                    // skip converting and cloning the arguments when no hook
+                    // needs them
+                    if hooks.0.is_empty() {
+                        return f($($p),*);
+                    }
+                    let args: Vec<ParameterValue> = vec![$($p.to_value()),*];
+                    let mut call = || f($($p.clone()),*).map(Return::to_value);
+                    let ret = Self::call(&hooks.0, name, &args, &mut call)?;
+                    R::from_value(ret)
+                }
+            }
+
+            /// Wraps a host function and registers it in the sandbox.
+            pub fn $register<$($P: Param,)* R: Return>(
+                &self,
+                sandbox: &mut UninitializedSandbox,
+                name: &'static str,
+                f: impl FnMut($($P),*) -> Result<R> + Send + 'static,
+            ) -> Result<()> {
+                let f = Arc::new(Mutex::new(self.$wrap(name, f)));
+                $trait::register(&f, sandbox, name)
+            }
+
+            /// Registers a host function that guests call asynchronously,
+            /// with `#[host_function] async fn`. See [`AsyncCalls`].
+            pub fn $register_async<$($P: Param,)* R: Return>(
+                &self,
+                sandbox: &mut UninitializedSandbox,
+                calls: &AsyncCalls,
+                name: &'static str,
+                f: impl Fn($($P),*) -> Result<R> + Send + Sync + 'static,
+            ) -> Result<()> {
+                let f = Arc::new(f);
+                let results: Results<R> = Default::default();
+
+                let calls = calls.clone();
+                let results_clone = results.clone();
+                self.$register(sandbox, name, move |$($p: $P),*| -> Result<u64> {
+                    let f = f.clone();
+                    Ok(calls.start(&results_clone, move || f($($p),*)))
+                })?;
+
+                // registered once per function, it's fine to leak it
+                let result_name = Box::leak(format!("{name}/Result").into_boxed_str());
+                self.register1(sandbox, result_name, move |id: u64| {
+                    AsyncCalls::result(&results, name, id)
+                })
+            }
+        }
+    };
+}
+
+impl_wrap_register!(wrap0, register0, register_async0, HostFunction0);
+impl_wrap_register!(wrap1, register1, register_async1, HostFunction1, p1: P1);
+impl_wrap_register!(wrap2, register2, register_async2, HostFunction2, p1: P1, p2: P2);
+impl_wrap_register!(wrap3, register3, register_async3, HostFunction3, p1: P1, p2: P2, p3: P3);
+impl_wrap_register!(wrap4, register4, register_async4, HostFunction4, p1: P1, p2: P2, p3: P3, p4: P4);
+impl_wrap_register!(wrap5, register5, register_async5, HostFunction5, p1: P1, p2: P2, p3: P3, p4: P4, p5: P5);
+impl_wrap_register!(wrap6, register6, register_async6, HostFunction6, p1: P1, p2: P2, p3: P3, p4: P4, p5: P5, p6: P6);
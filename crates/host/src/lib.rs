@@ -0,0 +1,11 @@
+pub mod async_calls;
+pub mod cancel;
+pub mod clock;
+pub mod events;
+pub mod hooks;
+pub mod logger;
+pub mod panic;
+pub mod replay;
+pub mod stats;
+pub mod trace;
+pub mod tty;
@@ -0,0 +1,41 @@
+use log::{Level, Record};
+
+/// Re-emits a record sent by the guest through the `GuestLog` host function
+/// using the host logger, so that it's subject to the same filtering as the host
+/// records (e.g., `RUST_LOG=guest_async=debug`).
+pub fn guest_log(
+    level: u32,
+    target: &str,
+    module_path: &str,
+    file: &str,
+    line: u32,
+    message: &str,
+) {
+    let level = match level {
+        1 => Level::Error,
+        2 => Level::Warn,
+        3 => Level::Info,
+        4 => Level::Debug,
+        _ => Level::Trace,
+    };
+    let metadata = log::Metadata::builder().level(level).target(target).build();
+    let logger = log::logger();
+    if !logger.enabled(&metadata) {
+        return;
+    }
+    logger.log(
+        &Record::builder()
+            .metadata(metadata)
+            .args(format_args!("{message}"))
+            .module_path(Some(module_path).filter(|m| !m.is_empty()))
+            .file(Some(file).filter(|f| !f.is_empty()))
+            .line(Some(line).filter(|_| !file.is_empty()))
+            .build(),
+    );
+}
+
+/// The most verbose level enabled for any target, used by the guest to
+/// avoid calling into the host for records that would be discarded anyway.
+pub fn max_level() -> u32 {
+    log::max_level() as u32
+}
@@ -6,26 +6,192 @@ use std::sync::Mutex as StdMutex;
 use std::time::Duration;
 
 use anyhow::{bail, Result};
-use clap::Parser;
-use hyperlight_host::func::HostFunction0 as _;
-use hyperlight_host::func::HostFunction1 as _;
+use clap::{Parser, ValueEnum};
 use hyperlight_host::func::ParameterValue;
 use hyperlight_host::func::ReturnValue;
 use hyperlight_host::sandbox::SandboxConfiguration;
 use hyperlight_host::GuestBinary;
 use hyperlight_host::HyperlightError;
+use tracing_chrome::FlushGuard;
 
 use hyperlight_host::func::ReturnType;
 use hyperlight_host::sandbox_state::sandbox::EvolvableSandbox;
 use hyperlight_host::sandbox_state::transition::Noop;
 use hyperlight_host::{MultiUseSandbox, UninitializedSandbox};
 
+use host::async_calls::AsyncCalls;
+use host::cancel::{CancelReason, Canceller};
+use host::clock::Clock;
+use host::events::{Event, EventQueue};
+use host::hooks::Hooks;
+use host::replay::{Recorder, Replayer};
+use host::stats::StatsCollector;
+use host::{logger, panic, trace, tty};
+
 mod stdin;
 
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum StatsFormat {
+    Table,
+    Json,
+}
+
 #[derive(Parser, Debug)]
 struct Args {
     /// Guest binary to execute
     guest: PathBuf,
+
+    /// Write a Chrome trace of the host and guest spans to this file
+    #[arg(long)]
+    trace_out: Option<PathBuf>,
+
+    /// Print execution statistics of the guest calls and host functions to stderr
+    #[arg(long, value_enum, num_args = 0..=1, default_missing_value = "table")]
+    stats: Option<StatsFormat>,
+
+    /// Record the host function calls made by the guest to this file
+    #[arg(long, conflicts_with = "replay")]
+    record: Option<PathBuf>,
+
+    /// Serve the host function calls from a recording made with `--record`
+    #[arg(long)]
+    replay: Option<PathBuf>,
+
+    /// Use a virtual clock that only advances when the guest waits, so that
+    /// sleeps and timeouts complete instantly
+    #[arg(long)]
+    virtual_time: bool,
+
+    /// After `Main` returns, keep running the tasks it spawned until they are
+    /// done or waiting for another call, calling `__RunPending` with a budget
+    /// of this many microseconds
+    #[arg(long, value_name = "BUDGET_US", num_args = 0..=1, default_missing_value = "10000")]
+    run_pending: Option<u64>,
+
+    /// Queue an event for the guest, with an optional payload
+    #[arg(long, value_name = "NAME[=DATA]")]
+    event: Vec<String>,
+
+    /// Cancel each guest call that runs for longer than this
+    #[arg(long, value_name = "SECONDS", value_parser = parse_seconds)]
+    timeout: Option<Duration>,
+
+    /// How long a cancelled guest call has to return, before the host kills
+    /// it by exiting
+    #[arg(long, value_name = "SECONDS", value_parser = parse_seconds, default_value = "5")]
+    grace_period: Duration,
+}
+
+fn parse_seconds(arg: &str) -> Result<Duration, String> {
+    let seconds = arg.parse::<f64>().map_err(|err| err.to_string())?;
+    Duration::try_from_secs_f64(seconds).map_err(|err| err.to_string())
+}
+
+/// The exit code of the host when the guest call is cancelled, like a shell
+/// for Ctrl-C and `timeout` for a timeout.
+fn exit_code(reason: CancelReason) -> i32 {
+    match reason {
+        CancelReason::Cancelled => 130,
+        CancelReason::TimedOut => 124,
+    }
+}
+
+/// The outputs written when the host exits, whether the guest call returned
+/// or was killed.
+struct Shutdown {
+    stats: StatsCollector,
+    stats_format: Option<StatsFormat>,
+    replayer: Option<Arc<Replayer>>,
+    // the guard of the trace, if it's enabled, taken once the outputs were
+    // written
+    trace_guard: StdMutex<Option<Option<FlushGuard>>>,
+}
+
+impl Shutdown {
+    /// Prints the stats, flushes the trace and checks that the replay is
+    /// complete, only the first time it's called.
+    ///
+    /// Called from the watchdog thread when the guest is killed, as the
+    /// thread running the guest call never returns then.
+    fn finish(&self) -> Result<()> {
+        // held until the outputs are written, so that the process doesn't
+        // exit from another thread in the meantime
+        let mut trace_guard = self.trace_guard.lock().unwrap();
+        let Some(guard) = trace_guard.take() else {
+            return Ok(());
+        };
+
+        match self.stats_format {
+            Some(StatsFormat::Table) => self.stats.stats().write_table(std::io::stderr())?,
+            Some(StatsFormat::Json) => self.stats.stats().write_json(std::io::stderr())?,
+            None => {}
+        }
+
+        // flush the trace before we exit
+        drop(guard);
+
+        if let Some(replayer) = &self.replayer {
+            replayer.finish()?;
+        }
+        Ok(())
+    }
+
+    /// Like [`Shutdown::finish`], then exits with `code`.
+    fn exit(&self, code: i32) -> ! {
+        if let Err(err) = self.finish() {
+            eprintln!("Error: {err:?}");
+        }
+        std::process::exit(code);
+    }
+}
+
+// What `PollReady` can wait for, as a bitmask
+const POLL_INPUT: u32 = 1;
+const POLL_EVENTS: u32 = 2;
+// Returned along with `POLL_INPUT` once stdin is closed and all its data was
+// read, so that the guest knows that reading it again returns nothing
+const POLL_CLOSED: u32 = 4;
+
+/// Waits until stdin has data or is closed, or an event is queued, depending
+/// on `interest`, for up to `timeout` microseconds (forever if zero), and
+/// returns which of them are ready.
+fn poll_ready(
+    stdin: &stdin::BlockingStdin,
+    events: &EventQueue,
+    clock: &Clock,
+    interest: u32,
+    timeout: u64,
+) -> u32 {
+    let timeout = Duration::from_micros(timeout);
+    let mut ready = 0;
+    let mut check = |has_data: bool, eof: bool| {
+        ready = 0;
+        if interest & POLL_INPUT != 0 && has_data {
+            ready |= POLL_INPUT;
+        } else if interest & POLL_INPUT != 0 && eof {
+            ready |= POLL_INPUT | POLL_CLOSED;
+        }
+        if interest & POLL_EVENTS != 0 && !events.is_empty() {
+            ready |= POLL_EVENTS;
+        }
+        ready != 0
+    };
+    if !clock.is_virtual() || timeout.is_zero() {
+        stdin.wait_until(timeout, check);
+        return ready;
+    }
+    // with a virtual clock, the timeout only elapses once there's no
+    // more input nor completion of an asynchronous host call coming. Other
+    // events can be pushed at any time, so they don't hold the clock.
+    let waits_input = interest & POLL_INPUT != 0;
+    let waits_events = interest & POLL_EVENTS != 0;
+    stdin.wait_until(Duration::ZERO, |has_data, eof| {
+        check(has_data, eof) || ((eof || !waits_input) && (!waits_events || events.is_idle()))
+    });
+    if ready == 0 {
+        clock.sleep(timeout);
+    }
+    ready
 }
 
 fn main() -> Result<()> {
@@ -33,48 +199,176 @@ fn main() -> Result<()> {
 
     env_logger::init();
 
+    let trace_guard = args.trace_out.as_deref().map(trace::init).transpose()?;
+
+    let stats = StatsCollector::default();
+
+    let mut hooks = Hooks::default();
+    if args.stats.is_some() {
+        hooks.push(Arc::new(stats.clone()));
+    }
+    if let Some(path) = &args.record {
+        hooks.push(Arc::new(Recorder::create(path)?));
+    }
+    let replayer = args.replay.as_deref().map(Replayer::open).transpose()?;
+    let replayer = replayer.map(Arc::new);
+    if let Some(replayer) = &replayer {
+        hooks.push(replayer.clone());
+    }
+
     let writer = move |msg: String| -> Result<i32, HyperlightError> {
         print!("{msg}");
         let _ = stdout().flush();
         Ok(msg.len() as i32)
     };
-    let writer = Arc::new(StdMutex::new(writer));
+    let writer = Arc::new(StdMutex::new(hooks.wrap1("HostPrint", writer)));
 
     let stdin = Arc::new(stdin::BlockingStdin::new());
-    stdin.spawn();
+    // a replay reads the recorded input instead
+    if args.replay.is_none() {
+        stdin.spawn();
+    }
 
     let stdin_clone = stdin.clone();
     let reader = move |count: u64| -> Result<Vec<u8>, HyperlightError> {
         Ok(stdin_clone.read(count as usize))
     };
-    let reader = Arc::new(StdMutex::new(reader));
 
     let stdin_clone = stdin.clone();
     let try_read = move |count: u64| -> Result<Vec<u8>, HyperlightError> {
         Ok(stdin_clone.try_read(count as usize))
     };
-    let try_read = Arc::new(StdMutex::new(try_read));
+
+    let clock = Arc::new(Clock::new(args.virtual_time));
+
+    let events = EventQueue::default();
+    let stdin_clone = stdin.clone();
+    events.on_push(move || stdin_clone.notify());
+    for event in &args.event {
+        let (name, data) = event.split_once('=').unwrap_or((event, ""));
+        events.push(Event::new(name, data));
+    }
 
     let stdin_clone = stdin.clone();
-    let poll_read = move |timeout: u64| -> Result<bool, HyperlightError> {
-        Ok(stdin_clone.poll_data(Duration::from_micros(timeout)))
+    let events_clone = events.clone();
+    let clock_clone = clock.clone();
+    let poll_ready = move |interest: u32, timeout: u64| -> Result<u32, HyperlightError> {
+        Ok(poll_ready(&stdin_clone, &events_clone, &clock_clone, interest, timeout))
+    };
+
+    let events_clone = events.clone();
+    let next_event = move || -> Result<Vec<u8>, HyperlightError> {
+        Ok(events_clone.pop().map(|event| event.encode()).unwrap_or_default())
     };
-    let poll_read = Arc::new(StdMutex::new(poll_read));
 
+    tty::watch_resize(events.clone())?;
+    let is_terminal = |fd: i32| -> Result<bool, HyperlightError> {
+        Ok(tty::is_terminal(fd))
+    };
+    let terminal_size = || -> Result<Vec<u8>, HyperlightError> {
+        Ok(tty::encode_size(tty::size()))
+    };
+    // the original mode is restored when the host exits
+    let set_raw_mode = |enabled: bool| -> Result<bool, HyperlightError> {
+        Ok(tty::set_raw_mode(enabled).is_ok())
+    };
+
+    // like `reader`, but the guest keeps running while it blocks
+    let calls = AsyncCalls::new(events.clone());
+    let stdin_clone = stdin.clone();
+    let read_async = move |count: u64| -> Result<Vec<u8>, HyperlightError> {
+        Ok(stdin_clone.read(count as usize))
+    };
+
+    let clock_clone = clock.clone();
     let time = move || -> Result<u64, HyperlightError> {
-        let now = std::time::SystemTime::now();
-        let now = now
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap_or_default();
-        Ok(now.as_micros() as u64)
+        Ok(clock_clone.now().as_micros() as u64)
+    };
+
+    let clock_clone = clock.clone();
+    let monotonic_time = move || -> Result<u64, HyperlightError> {
+        Ok(clock_clone.monotonic().as_micros() as u64)
+    };
+
+    let clock_clone = clock.clone();
+    let sleep = move |duration: u64| -> Result<(), HyperlightError> {
+        clock_clone.sleep(Duration::from_micros(duration));
+        Ok(())
+    };
+
+    let panics = panic::PanicReporter::default();
+    let panics_clone = panics.clone();
+    let report_panic = move |message: String,
+                             file: String,
+                             line: u32,
+                             backtrace: Vec<u8>|
+          -> Result<(), HyperlightError> {
+        panics_clone.report(message, file, line, backtrace);
+        Ok(())
+    };
+
+    let guest_log = |level: u32,
+                     target: String,
+                     module_path: String,
+                     file: String,
+                     line: u32,
+                     message: String|
+     -> Result<(), HyperlightError> {
+        logger::guest_log(level, &target, &module_path, &file, line, &message);
+        Ok(())
+    };
+
+    let log_level = || -> Result<u32, HyperlightError> { Ok(logger::max_level()) };
+
+    let tracer = Arc::new(trace::GuestTracer::default());
+
+    let trace_enabled = args.trace_out.is_some();
+    let trace_enabled = move || -> Result<bool, HyperlightError> { Ok(trace_enabled) };
+
+    let tracer_clone = tracer.clone();
+    let span_new = move |id: u64,
+                         parent: u64,
+                         name: String,
+                         target: String,
+                         fields: String|
+          -> Result<(), HyperlightError> {
+        tracer_clone.new_span(id, parent, &name, &target, &fields);
+        Ok(())
+    };
+
+    let tracer_clone = tracer.clone();
+    let span_record = move |id: u64, fields: String| -> Result<(), HyperlightError> {
+        tracer_clone.record(id, &fields);
+        Ok(())
+    };
+
+    let tracer_clone = tracer.clone();
+    let span_enter = move |id: u64| -> Result<(), HyperlightError> {
+        tracer_clone.enter(id);
+        Ok(())
+    };
+
+    let tracer_clone = tracer.clone();
+    let span_exit = move |id: u64| -> Result<(), HyperlightError> {
+        tracer_clone.exit(id);
+        Ok(())
+    };
+
+    let tracer_clone = tracer.clone();
+    let span_close = move |id: u64| -> Result<(), HyperlightError> {
+        tracer_clone.close(id);
+        Ok(())
     };
-    let time = Arc::new(StdMutex::new(time));
 
-    let sleep = |duration: u64| -> Result<(), HyperlightError> {
-        std::thread::sleep(Duration::from_micros(duration));
+    let tracer_clone = tracer.clone();
+    let trace_event = move |parent: u64,
+                            level: u32,
+                            target: String,
+                            fields: String|
+          -> Result<(), HyperlightError> {
+        tracer_clone.event(parent, level, &target, &fields);
         Ok(())
     };
-    let sleep = Arc::new(StdMutex::new(sleep));
 
     let mut cfg = SandboxConfiguration::default();
     cfg.set_kernel_stack_size(2 * 1024 * 1024);
@@ -90,20 +384,108 @@ fn main() -> Result<()> {
         Some(&writer),
     )?;
 
-    reader.register(&mut sandbox, "HostInput")?;
-    try_read.register(&mut sandbox, "TryInput")?;
-    poll_read.register(&mut sandbox, "PollInput")?;
-    time.register(&mut sandbox, "GetTime")?;
-    sleep.register(&mut sandbox, "Sleep")?;
+    hooks.register1(&mut sandbox, "HostInput", reader)?;
+    hooks.register_async1(&mut sandbox, &calls, "HostInputAsync", read_async)?;
+    hooks.register1(&mut sandbox, "TryInput", try_read)?;
+    hooks.register2(&mut sandbox, "PollReady", poll_ready)?;
+    hooks.register0(&mut sandbox, "NextEvent", next_event)?;
+    hooks.register1(&mut sandbox, "IsTerminal", is_terminal)?;
+    hooks.register0(&mut sandbox, "TerminalSize", terminal_size)?;
+    hooks.register1(&mut sandbox, "SetRawMode", set_raw_mode)?;
+    hooks.register0(&mut sandbox, "GetTime", time)?;
+    hooks.register0(&mut sandbox, "GetMonotonicTime", monotonic_time)?;
+    hooks.register1(&mut sandbox, "Sleep", sleep)?;
+    hooks.register4(&mut sandbox, "GuestPanic", report_panic)?;
+    hooks.register6(&mut sandbox, "GuestLog", guest_log)?;
+    hooks.register0(&mut sandbox, "GetLogLevel", log_level)?;
+    hooks.register0(&mut sandbox, "TraceEnabled", trace_enabled)?;
+    hooks.register5(&mut sandbox, "TraceSpanNew", span_new)?;
+    hooks.register2(&mut sandbox, "TraceSpanRecord", span_record)?;
+    hooks.register1(&mut sandbox, "TraceSpanEnter", span_enter)?;
+    hooks.register1(&mut sandbox, "TraceSpanExit", span_exit)?;
+    hooks.register1(&mut sandbox, "TraceSpanClose", span_close)?;
+    hooks.register4(&mut sandbox, "TraceEvent", trace_event)?;
 
     let mut sandbox: MultiUseSandbox = sandbox.evolve(Noop::default())?;
 
+    let shutdown = Arc::new(Shutdown {
+        stats: stats.clone(),
+        stats_format: args.stats,
+        replayer: replayer.clone(),
+        trace_guard: StdMutex::new(Some(trace_guard)),
+    });
+
+    // On Ctrl-C, ask the guest to stop, and kill it if it doesn't return in
+    // time or on a second Ctrl-C. There's no way to interrupt a single call,
+    // so killing it exits, once the outputs are written.
+    let grace_period = args.grace_period;
+    let shutdown_clone = shutdown.clone();
+    let canceller = Canceller::new(events.clone(), grace_period, move |reason| {
+        eprintln!("The guest call {reason}, and didn't return within {grace_period:?}: killing it");
+        shutdown_clone.exit(exit_code(reason));
+    });
+    let canceller_clone = canceller.clone();
+    let shutdown_clone = shutdown.clone();
+    ctrlc::set_handler(move || {
+        if !canceller_clone.cancel() {
+            shutdown_clone.exit(exit_code(CancelReason::Cancelled));
+        }
+    })?;
+
     // Call guest function
-    let result = sandbox.call_guest_function_by_name(
-        "Main", // function must be defined in the guest binary
-        ReturnType::Int,
-        Some(vec![ParameterValue::String("my friend".to_string())]),
-    )?;
+    let (result, mut cancelled) = tracer.call("Main", || {
+        canceller.call(args.timeout, || {
+            stats.guest_call("Main", || {
+                sandbox.call_guest_function_by_name(
+                    "Main", // function must be defined in the guest binary
+                    ReturnType::Int,
+                    Some(vec![ParameterValue::String("my friend".to_string())]),
+                )
+            })
+        })
+    });
+
+    let result = match (result, args.run_pending) {
+        (Ok(result), Some(budget_us)) if cancelled.is_none() => {
+            let pending = run_pending(
+                &mut sandbox,
+                &stats,
+                &tracer,
+                &canceller,
+                args.timeout,
+                budget_us,
+            );
+            pending.map(|reason| {
+                cancelled = reason;
+                result
+            })
+        }
+        (result, _) => result,
+    };
+
+    shutdown.finish()?;
+
+    if let Some(reason) = cancelled {
+        eprintln!("The guest call {reason}");
+        std::process::exit(exit_code(reason));
+    }
+
+    let result = match result {
+        Ok(result) => result,
+        Err(HyperlightError::GuestAborted(code, message)) => {
+            // panics that hl-guest didn't report are caught by the panic
+            // handler of hyperlight_guest, which aborts with their message
+            let report = panics
+                .take()
+                .or_else(|| panic::PanicReport::from_abort(&message));
+            match report {
+                Some(report) => report.print(&args.guest),
+                None => panic::print_abort(code, &message),
+            }
+            std::process::exit(101);
+        }
+        Err(err) => return Err(err.into()),
+    };
 
     let ReturnValue::Int(result) = result else {
         bail!("Expected an integer return value");
@@ -115,3 +497,40 @@ fn main() -> Result<()> {
 
     Ok(())
 }
+
+/// Calls `__RunPending` until none of the guest's tasks can make progress,
+/// or until a call is cancelled, and returns why.
+fn run_pending(
+    sandbox: &mut MultiUseSandbox,
+    stats: &StatsCollector,
+    tracer: &trace::GuestTracer,
+    canceller: &Canceller,
+    timeout: Option<Duration>,
+    budget_us: u64,
+) -> Result<Option<CancelReason>, HyperlightError> {
+    loop {
+        let (pending, cancelled) = tracer.call("__RunPending", || {
+            canceller.call(timeout, || {
+                stats.guest_call("__RunPending", || {
+                    sandbox.call_guest_function_by_name(
+                        "__RunPending",
+                        ReturnType::ULong,
+                        Some(vec![ParameterValue::ULong(budget_us)]),
+                    )
+                })
+            })
+        });
+        if cancelled.is_some() {
+            return Ok(cancelled);
+        }
+        match pending? {
+            ReturnValue::ULong(0) => return Ok(None),
+            ReturnValue::ULong(_) => {}
+            _ => {
+                return Err(HyperlightError::Error(
+                    "Expected an integer return value from __RunPending".to_string(),
+                ))
+            }
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use goblin::elf::sym::STT_FUNC;
+use goblin::elf::Elf;
+
+// Name of the symbol the guest uses as a reference to compute its load address
+const BASE_SYMBOL: &str = "hyperlight_main";
+
+pub struct PanicReport {
+    message: String,
+    file: String,
+    line: u32,
+    base: Option<u64>,
+    frames: Vec<u64>,
+    // whether the guest sent it through `GuestPanic`, instead of aborting
+    reported: bool,
+}
+
+/// Collects the panic reports sent by the guest through the `GuestPanic` host function.
+#[derive(Clone, Default)]
+pub struct PanicReporter(Arc<Mutex<Option<PanicReport>>>);
+
+impl PanicReporter {
+    pub fn report(&self, message: String, file: String, line: u32, backtrace: Vec<u8>) {
+        let mut addresses = backtrace
+            .chunks_exact(8)
+            .map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap()));
+        let base = addresses.next();
+        let frames = addresses.collect();
+        *self.0.lock().unwrap() = Some(PanicReport {
+            message,
+            file,
+            line,
+            base,
+            frames,
+            reported: true,
+        });
+    }
+
+    pub fn take(&self) -> Option<PanicReport> {
+        self.0.lock().unwrap().take()
+    }
+}
+
+impl PanicReport {
+    /// Builds a report from the message of a guest abort, for the panics
+    /// that the `hyperlight_guest` panic handler caught without them being
+    /// reported through `GuestPanic`.
+    ///
+    /// The handler formats the panic as `panicked at <file>:<line>:<column>:`
+    /// followed by the message on the next line.
+    pub fn from_abort(message: &str) -> Option<Self> {
+        let message = message.strip_prefix("panicked at ")?;
+        let (location, message) = message.split_once(":\n").unwrap_or((message, ""));
+        let mut parts = location.rsplitn(3, ':');
+        let _column = parts.next()?;
+        let line = parts.next()?.parse().ok()?;
+        let file = parts.next()?;
+        Some(PanicReport {
+            message: message.to_string(),
+            file: file.to_string(),
+            line,
+            base: None,
+            frames: Vec::new(),
+            reported: false,
+        })
+    }
+
+    /// Prints the report to stderr, symbolizing the backtrace with the guest binary.
+    pub fn print(&self, guest: &Path) {
+        eprintln!("guest panicked at {}:{}:", self.file, self.line);
+        eprintln!("{}", self.message);
+
+        let (Some(base), false) = (self.base, self.frames.is_empty()) else {
+            if self.reported {
+                eprintln!("note: build hl-guest with the `backtrace` feature to get a backtrace");
+            }
+            return;
+        };
+
+        let symbolizer = match Symbolizer::new(guest) {
+            Ok(symbolizer) => Some(symbolizer),
+            Err(err) => {
+                eprintln!("note: failed to load symbols from {guest:?}: {err}");
+                None
+            }
+        };
+        let bias = symbolizer
+            .as_ref()
+            .and_then(|s| s.address_of(BASE_SYMBOL))
+            .map(|addr| base.wrapping_sub(addr));
+
+        eprintln!("stack backtrace:");
+        for (i, frame) in self.frames.iter().enumerate() {
+            let symbol = symbolizer
+                .as_ref()
+                .zip(bias)
+                .and_then(|(symbolizer, bias)| {
+                    // the return address points to the instruction after the call
+                    symbolizer.lookup(frame.wrapping_sub(bias).wrapping_sub(1))
+                });
+            match symbol {
+                Some((name, offset)) => {
+                    eprintln!("{i:4}: {frame:#018x} - {name}+{:#x}", offset + 1)
+                }
+                None => eprintln!("{i:4}: {frame:#018x} - <unknown>"),
+            }
+        }
+    }
+}
+
+/// Prints the message of a guest abort that isn't a panic.
+pub fn print_abort(code: u8, message: &str) {
+    eprintln!("guest aborted with code {code}: {message}");
+}
+
+struct Symbol {
+    address: u64,
+    size: u64,
+    name: String,
+}
+
+struct Symbolizer {
+    symbols: Vec<Symbol>,
+}
+
+impl Symbolizer {
+    fn new(path: &Path) -> Result<Self> {
+        let bytes = std::fs::read(path)?;
+        let elf = Elf::parse(&bytes)?;
+        let mut symbols: Vec<_> = elf
+            .syms
+            .iter()
+            .filter(|sym| sym.st_type() == STT_FUNC && sym.st_value != 0)
+            .filter_map(|sym| {
+                let name = elf.strtab.get_at(sym.st_name)?;
+                Some(Symbol {
+                    address: sym.st_value,
+                    size: sym.st_size,
+                    name: format!("{:#}", rustc_demangle::demangle(name)),
+                })
+            })
+            .collect();
+        symbols.sort_by_key(|sym| sym.address);
+        Ok(Self { symbols })
+    }
+
+    fn address_of(&self, name: &str) -> Option<u64> {
+        self.symbols
+            .iter()
+            .find(|sym| sym.name == name)
+            .map(|sym| sym.address)
+    }
+
+    fn lookup(&self, address: u64) -> Option<(&str, u64)> {
+        let idx = self.symbols.partition_point(|sym| sym.address <= address);
+        let sym = &self.symbols[idx.checked_sub(1)?];
+        let offset = address - sym.address;
+        if sym.size != 0 && offset >= sym.size {
+            return None;
+        }
+        Some((&sym.name, offset))
+    }
+}
@@ -0,0 +1,231 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufRead as _, BufReader, BufWriter, Write as _};
+use std::path::Path;
+use std::sync::Mutex;
+
+use hyperlight_host::func::{ParameterValue, ReturnValue};
+use hyperlight_host::{HyperlightError, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::hooks::Hook;
+
+/// Host functions that only have side effects on the host (output, logging,
+/// tracing). They still run when replaying, so that the output is reproduced.
+///
+/// The terminal functions (`IsTerminal`, `TerminalSize`, `SetRawMode`) are
+/// replayed instead: their results depend on the terminal of the recording,
+/// and a replay doesn't read stdin, so it must not switch it to raw mode.
+const PASSTHROUGH: &[&str] = &[
+    "HostPrint",
+    "GuestPanic",
+    "GuestLog",
+    "TraceSpanNew",
+    "TraceSpanRecord",
+    "TraceSpanEnter",
+    "TraceSpanExit",
+    "TraceSpanClose",
+    "TraceEvent",
+];
+
+/// A host function call, as stored in a recording (one JSON object per line).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RecordedCall {
+    pub name: String,
+    pub args: Vec<Value>,
+    pub result: std::result::Result<Value, String>,
+}
+
+/// A serializable [`ParameterValue`] or [`ReturnValue`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Value {
+    Int(i32),
+    UInt(u32),
+    Long(i64),
+    ULong(u64),
+    Float(f32),
+    Double(f64),
+    Bool(bool),
+    String(String),
+    VecBytes(Vec<u8>),
+    Void,
+}
+
+impl From<&ParameterValue> for Value {
+    fn from(value: &ParameterValue) -> Self {
+        match value.clone() {
+            ParameterValue::Int(v) => Value::Int(v),
+            ParameterValue::UInt(v) => Value::UInt(v),
+            ParameterValue::Long(v) => Value::Long(v),
+            ParameterValue::ULong(v) => Value::ULong(v),
+            ParameterValue::Float(v) => Value::Float(v),
+            ParameterValue::Double(v) => Value::Double(v),
+            ParameterValue::Bool(v) => Value::Bool(v),
+            ParameterValue::String(v) => Value::String(v),
+            ParameterValue::VecBytes(v) => Value::VecBytes(v),
+        }
+    }
+}
+
+impl From<&ReturnValue> for Value {
+    fn from(value: &ReturnValue) -> Self {
+        match value.clone() {
+            ReturnValue::Int(v) => Value::Int(v),
+            ReturnValue::UInt(v) => Value::UInt(v),
+            ReturnValue::Long(v) => Value::Long(v),
+            ReturnValue::ULong(v) => Value::ULong(v),
+            ReturnValue::Float(v) => Value::Float(v),
+            ReturnValue::Double(v) => Value::Double(v),
+            ReturnValue::Bool(v) => Value::Bool(v),
+            ReturnValue::String(v) => Value::String(v),
+            ReturnValue::VecBytes(v) => Value::VecBytes(v),
+            ReturnValue::Void => Value::Void,
+        }
+    }
+}
+
+impl From<Value> for ReturnValue {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Int(v) => ReturnValue::Int(v),
+            Value::UInt(v) => ReturnValue::UInt(v),
+            Value::Long(v) => ReturnValue::Long(v),
+            Value::ULong(v) => ReturnValue::ULong(v),
+            Value::Float(v) => ReturnValue::Float(v),
+            Value::Double(v) => ReturnValue::Double(v),
+            Value::Bool(v) => ReturnValue::Bool(v),
+            Value::String(v) => ReturnValue::String(v),
+            Value::VecBytes(v) => ReturnValue::VecBytes(v),
+            Value::Void => ReturnValue::Void,
+        }
+    }
+}
+
+/// A [`Hook`] that writes every host function call to a file.
+///
+/// Calls are flushed as they happen, so the recording is complete even if the
+/// guest aborts.
+pub struct Recorder(Mutex<BufWriter<File>>);
+
+impl Recorder {
+    pub fn create(path: &Path) -> std::io::Result<Self> {
+        Ok(Self(Mutex::new(BufWriter::new(File::create(path)?))))
+    }
+}
+
+impl Hook for Recorder {
+    fn call(
+        &self,
+        name: &str,
+        args: &[ParameterValue],
+        next: &mut dyn FnMut() -> Result<ReturnValue>,
+    ) -> Result<ReturnValue> {
+        let result = next();
+        let call = RecordedCall {
+            name: name.to_string(),
+            args: args.iter().map(Value::from).collect(),
+            result: match &result {
+                Ok(value) => Ok(value.into()),
+                Err(err) => Err(err.to_string()),
+            },
+        };
+
+        let mut file = self.0.lock().unwrap();
+        serde_json::to_writer(&mut *file, &call).map_err(io_error)?;
+        writeln!(file)
+            .and_then(|_| file.flush())
+            .map_err(io_error)?;
+
+        result
+    }
+}
+
+/// A [`Hook`] that serves the results of a recording instead of calling the
+/// host functions.
+///
+/// Every call must match the next recorded one, by name and arguments. On the
+/// first divergence, that call and all the following ones fail, and
+/// [`Replayer::finish`] reports it.
+pub struct Replayer {
+    calls: Mutex<VecDeque<RecordedCall>>,
+    divergence: Mutex<Option<String>>,
+}
+
+impl Replayer {
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let mut calls = VecDeque::new();
+        for (n, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+            let call = serde_json::from_str(&line?).map_err(|err| {
+                anyhow::anyhow!("Invalid recording {}:{}: {err}", path.display(), n + 1)
+            })?;
+            calls.push_back(call);
+        }
+        Ok(Self {
+            calls: Mutex::new(calls),
+            divergence: Mutex::default(),
+        })
+    }
+
+    /// Checks that the guest didn't diverge from the recording, and that it
+    /// made all the recorded calls.
+    pub fn finish(&self) -> anyhow::Result<()> {
+        if let Some(divergence) = self.divergence.lock().unwrap().take() {
+            anyhow::bail!("Replay diverged from the recording: {divergence}");
+        }
+        let calls = self.calls.lock().unwrap();
+        if let Some(call) = calls.front() {
+            anyhow::bail!(
+                "Replay diverged from the recording: {} recorded calls were not made, starting with {}{:?}",
+                calls.len(),
+                call.name,
+                call.args,
+            );
+        }
+        Ok(())
+    }
+
+    fn diverge(&self, divergence: String) -> HyperlightError {
+        eprintln!("replay diverged from the recording: {divergence}");
+        let err = HyperlightError::Error(format!("Replay diverged: {divergence}"));
+        self.divergence.lock().unwrap().get_or_insert(divergence);
+        err
+    }
+}
+
+impl Hook for Replayer {
+    fn call(
+        &self,
+        name: &str,
+        args: &[ParameterValue],
+        next: &mut dyn FnMut() -> Result<ReturnValue>,
+    ) -> Result<ReturnValue> {
+        if let Some(divergence) = &*self.divergence.lock().unwrap() {
+            return Err(HyperlightError::Error(format!(
+                "Replay diverged: {divergence}"
+            )));
+        }
+
+        let args: Vec<Value> = args.iter().map(Value::from).collect();
+        let Some(call) = self.calls.lock().unwrap().pop_front() else {
+            return Err(self.diverge(format!("unexpected call {name}{args:?}")));
+        };
+        if call.name != name || call.args != args {
+            return Err(self.diverge(format!(
+                "expected call {}{:?}, but got {name}{args:?}",
+                call.name, call.args,
+            )));
+        }
+
+        if PASSTHROUGH.contains(&name) {
+            next()?;
+        }
+        match call.result {
+            Ok(value) => Ok(value.into()),
+            Err(err) => Err(HyperlightError::Error(err)),
+        }
+    }
+}
+
+fn io_error(err: impl Into<std::io::Error>) -> HyperlightError {
+    HyperlightError::Error(format!("Failed to write the recording: {}", err.into()))
+}
@@ -0,0 +1,147 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use hyperlight_host::func::{ParameterValue, ReturnValue};
+use hyperlight_host::Result;
+use serde::Serialize;
+
+use crate::hooks::Hook;
+
+/// Execution statistics of a sandbox.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Stats {
+    pub guest_calls: Vec<GuestCallStats>,
+    pub host_functions: BTreeMap<String, HostFunctionStats>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct GuestCallStats {
+    pub name: String,
+    #[serde(serialize_with = "as_micros")]
+    pub wall_time: Duration,
+    pub ok: bool,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct HostFunctionStats {
+    pub count: u64,
+    #[serde(serialize_with = "as_micros")]
+    pub total_time: Duration,
+    #[serde(serialize_with = "as_micros")]
+    pub max_time: Duration,
+    /// Bytes received from the guest as arguments
+    pub bytes_in: u64,
+    /// Bytes returned to the guest
+    pub bytes_out: u64,
+}
+
+/// Collects [`Stats`] from the guest calls and, when added to the
+/// [`Hooks`](crate::hooks::Hooks), from the host function calls.
+#[derive(Clone, Default)]
+pub struct StatsCollector(Arc<Mutex<Stats>>);
+
+impl StatsCollector {
+    /// Runs a guest call, `f`, recording how long it took.
+    pub fn guest_call<T>(&self, name: &str, f: impl FnOnce() -> Result<T>) -> Result<T> {
+        let start = Instant::now();
+        let result = f();
+        self.0.lock().unwrap().guest_calls.push(GuestCallStats {
+            name: name.to_string(),
+            wall_time: start.elapsed(),
+            ok: result.is_ok(),
+        });
+        result
+    }
+
+    pub fn stats(&self) -> Stats {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+impl Hook for StatsCollector {
+    fn call(
+        &self,
+        name: &str,
+        args: &[ParameterValue],
+        next: &mut dyn FnMut() -> Result<ReturnValue>,
+    ) -> Result<ReturnValue> {
+        let start = Instant::now();
+        let result = next();
+        let elapsed = start.elapsed();
+
+        let mut stats = self.0.lock().unwrap();
+        let stats = stats.host_functions.entry(name.to_string()).or_default();
+        stats.count += 1;
+        stats.total_time += elapsed;
+        stats.max_time = stats.max_time.max(elapsed);
+        stats.bytes_in += args.iter().map(param_size).sum::<u64>();
+        if let Ok(ret) = &result {
+            stats.bytes_out += return_size(ret);
+        }
+
+        result
+    }
+}
+
+impl Stats {
+    pub fn write_table(&self, mut w: impl Write) -> std::io::Result<()> {
+        writeln!(w, "{:<24} {:>12} {:>6}", "guest call", "wall time", "ok")?;
+        for call in &self.guest_calls {
+            let wall_time = format!("{:.3?}", call.wall_time);
+            writeln!(w, "{:<24} {:>12} {:>6}", call.name, wall_time, call.ok)?;
+        }
+        writeln!(w)?;
+        writeln!(
+            w,
+            "{:<24} {:>8} {:>12} {:>12} {:>12} {:>10} {:>10}",
+            "host function", "count", "total", "mean", "max", "bytes in", "bytes out"
+        )?;
+        for (name, stats) in &self.host_functions {
+            let mean = stats.total_time / stats.count.max(1) as u32;
+            writeln!(
+                w,
+                "{:<24} {:>8} {:>12} {:>12} {:>12} {:>10} {:>10}",
+                name,
+                stats.count,
+                format!("{:.3?}", stats.total_time),
+                format!("{mean:.3?}"),
+                format!("{:.3?}", stats.max_time),
+                stats.bytes_in,
+                stats.bytes_out,
+            )?;
+        }
+        Ok(())
+    }
+
+    pub fn write_json(&self, w: impl Write) -> std::io::Result<()> {
+        serde_json::to_writer_pretty(w, self)?;
+        Ok(())
+    }
+}
+
+fn as_micros<S: serde::Serializer>(d: &Duration, s: S) -> std::result::Result<S::Ok, S::Error> {
+    s.serialize_u64(d.as_micros() as u64)
+}
+
+fn param_size(value: &ParameterValue) -> u64 {
+    match value {
+        ParameterValue::Int(_) | ParameterValue::UInt(_) | ParameterValue::Float(_) => 4,
+        ParameterValue::Long(_) | ParameterValue::ULong(_) | ParameterValue::Double(_) => 8,
+        ParameterValue::Bool(_) => 1,
+        ParameterValue::String(s) => s.len() as u64,
+        ParameterValue::VecBytes(v) => v.len() as u64,
+    }
+}
+
+fn return_size(value: &ReturnValue) -> u64 {
+    match value {
+        ReturnValue::Int(_) | ReturnValue::UInt(_) | ReturnValue::Float(_) => 4,
+        ReturnValue::Long(_) | ReturnValue::ULong(_) | ReturnValue::Double(_) => 8,
+        ReturnValue::Bool(_) => 1,
+        ReturnValue::String(s) => s.len() as u64,
+        ReturnValue::VecBytes(v) => v.len() as u64,
+        ReturnValue::Void => 0,
+    }
+}
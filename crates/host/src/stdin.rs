@@ -1,29 +1,23 @@
 use std::io::{stdin, Read as _};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Condvar, Mutex};
 use std::time::Duration;
 
-pub struct BlockingStdin(Arc<(Mutex<Vec<u8>>, Condvar)>);
+// The buffered data, a condvar notified when data is added or stdin is closed,
+// and whether stdin is closed (only set while holding the buffer lock)
+pub struct BlockingStdin(Arc<(Mutex<Vec<u8>>, Condvar, AtomicBool)>);
 
 impl BlockingStdin {
+    /// Creates an empty buffer, see `spawn` to fill it from stdin.
     pub fn new() -> Self {
-        let inner = Arc::new((Mutex::new(Vec::new()), Condvar::new()));
-        let inner2 = inner.clone();
-        std::thread::spawn(move || {
-            let mut stdin = stdin();
-            let mut buf = vec![0u8; 1024];
-            loop {
-                let n = stdin.read(&mut buf).unwrap();
-                if n == 0 {
-                    break;
-                }
-                let mut buffer = inner2.0.lock().unwrap();
-                buffer.extend_from_slice(&buf[..n]);
-                inner2.1.notify_all();
-            }
-        });
-        BlockingStdin(inner)
+        BlockingStdin(Arc::new((
+            Mutex::new(Vec::new()),
+            Condvar::new(),
+            AtomicBool::new(false),
+        )))
     }
 
+    /// Starts the thread reading stdin into the buffer. Only call it once.
     pub fn spawn(&self) {
         let inner = self.0.clone();
         std::thread::spawn(move || {
@@ -32,6 +26,9 @@ impl BlockingStdin {
             loop {
                 let n = stdin.read(&mut buf).unwrap();
                 if n == 0 {
+                    let _buffer = inner.0.lock().unwrap();
+                    inner.2.store(true, Ordering::Relaxed);
+                    inner.1.notify_all();
                     break;
                 }
                 let mut buffer = inner.0.lock().unwrap();
@@ -52,18 +49,32 @@ impl BlockingStdin {
         return tail;
     }
 
-    pub fn poll_data(&self, timeout: Duration) -> bool {
+    /// Waits until `ready` returns true, for up to `timeout` (forever if
+    /// zero), and returns its last result.
+    ///
+    /// `ready` is given whether there's data to read and whether stdin is
+    /// closed, and is checked again when they change or `notify` is called.
+    pub fn wait_until(&self, timeout: Duration, mut ready: impl FnMut(bool, bool) -> bool) -> bool {
+        let eof = &self.0 .2;
+        let mut ready = |buffer: &mut Vec<u8>| ready(!buffer.is_empty(), eof.load(Ordering::Relaxed));
         let buffer = self.0 .0.lock().unwrap();
-        let buffer = if timeout.is_zero() {
-            self.0 .1.wait_while(buffer, |b| b.is_empty()).unwrap()
+        let mut buffer = if timeout.is_zero() {
+            self.0 .1.wait_while(buffer, |b| !ready(b)).unwrap()
         } else {
             self.0
                  .1
-                .wait_timeout_while(buffer, timeout, |b| b.is_empty())
+                .wait_timeout_while(buffer, timeout, |b| !ready(b))
                 .unwrap()
                 .0
         };
-        !buffer.is_empty()
+        ready(&mut buffer)
+    }
+
+    /// Wakes up the threads in `wait_until`, so that they check their
+    /// condition again.
+    pub fn notify(&self) {
+        let _buffer = self.0 .0.lock().unwrap();
+        self.0 .1.notify_all();
     }
 
     pub fn read(&self, count: usize) -> Vec<u8> {
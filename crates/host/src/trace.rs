@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::path::Path;
+use std::sync::Mutex;
+
+use anyhow::Result;
+use tracing::field::{Field, Visit};
+use tracing::span::Attributes;
+use tracing::{Id, Level, Span, Subscriber};
+use tracing_chrome::{ChromeLayerBuilder, EventOrSpan, FlushGuard, TraceStyle};
+use tracing_subscriber::layer::{Context, SubscriberExt as _};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::{Layer, Registry};
+
+// Target of the host spans and events that mirror the guest ones
+const GUEST_TARGET: &str = "guest";
+
+/// Installs a global subscriber that writes a Chrome trace to `path`.
+///
+/// The trace is flushed when the returned guard is dropped.
+pub fn init(path: &Path) -> Result<FlushGuard> {
+    let (chrome, guard) = ChromeLayerBuilder::new()
+        .file(path)
+        .include_args(true)
+        // guest spans are entered from the thread running the vCPU,
+        // tracking them by span rather than by thread keeps them nested
+        // under the host span around the guest call.
+        .trace_style(TraceStyle::Async)
+        .name_fn(Box::new(|event_or_span| match event_or_span {
+            EventOrSpan::Event(event) => {
+                let mut name = Name::default();
+                event.record(&mut name);
+                name.0
+                    .unwrap_or_else(|| event.metadata().name().to_string())
+            }
+            EventOrSpan::Span(span) => match span.extensions().get::<GuestSpanName>() {
+                Some(name) => name.0.clone(),
+                None => span.name().to_string(),
+            },
+        }))
+        .build();
+    let subscriber = Registry::default().with(GuestSpanNameLayer).with(chrome);
+    tracing::subscriber::set_global_default(subscriber)?;
+    Ok(guard)
+}
+
+/// Mirrors the spans and events reported by the guest as host spans and events.
+#[derive(Default)]
+pub struct GuestTracer {
+    parent: Mutex<Option<Span>>,
+    spans: Mutex<HashMap<u64, GuestSpan>>,
+}
+
+struct GuestSpan {
+    span: Span,
+    // all the fields recorded so far, as the `fields` of `span` only holds
+    // the last value recorded
+    fields: String,
+}
+
+impl GuestTracer {
+    /// Runs `f`, a call to the guest function `name`, in a span that the
+    /// guest spans without a parent are attached to.
+    pub fn call<R>(&self, name: &str, f: impl FnOnce() -> R) -> R {
+        let span = tracing::info_span!("call_guest_function", name);
+        *self.parent.lock().unwrap() = Some(span.clone());
+        let result = span.in_scope(f);
+        // the span only ends once all its clones are dropped
+        self.parent.lock().unwrap().take();
+        result
+    }
+
+    fn span(&self, id: u64) -> Span {
+        if let Some(span) = self.spans.lock().unwrap().get(&id) {
+            return span.span.clone();
+        }
+        self.parent
+            .lock()
+            .unwrap()
+            .clone()
+            .unwrap_or_else(Span::none)
+    }
+
+    pub fn new_span(&self, id: u64, parent: u64, name: &str, target: &str, fields: &str) {
+        let parent = self.span(parent);
+        let span = tracing::info_span!(
+            target: GUEST_TARGET,
+            parent: &parent,
+            "guest_span",
+            name,
+            guest_target = target,
+            fields,
+        );
+        let fields = fields.to_string();
+        self.spans
+            .lock()
+            .unwrap()
+            .insert(id, GuestSpan { span, fields });
+    }
+
+    /// Adds `fields` to the ones of the span. A field recorded again shows
+    /// up twice, the last one being its current value.
+    pub fn record(&self, id: u64, fields: &str) {
+        if let Some(span) = self.spans.lock().unwrap().get_mut(&id) {
+            if !span.fields.is_empty() && !fields.is_empty() {
+                span.fields.push(' ');
+            }
+            span.fields.push_str(fields);
+            span.span.record("fields", span.fields.as_str());
+        }
+    }
+
+    // The enter and exit calls come from different host function calls, so we
+    // can't use the `Span::enter` guard.
+    pub fn enter(&self, id: u64) {
+        if let Some(span) = self.spans.lock().unwrap().get(&id) {
+            span.span
+                .with_subscriber(|(id, dispatch)| dispatch.enter(id));
+        }
+    }
+
+    pub fn exit(&self, id: u64) {
+        if let Some(span) = self.spans.lock().unwrap().get(&id) {
+            span.span
+                .with_subscriber(|(id, dispatch)| dispatch.exit(id));
+        }
+    }
+
+    pub fn close(&self, id: u64) {
+        self.spans.lock().unwrap().remove(&id);
+    }
+
+    pub fn event(&self, parent: u64, level: u32, target: &str, fields: &str) {
+        let parent = self.span(parent);
+        macro_rules! event {
+            ($level:expr) => {
+                tracing::event!(
+                    target: GUEST_TARGET,
+                    parent: &parent,
+                    $level,
+                    guest_target = target,
+                    "{}",
+                    fields
+                )
+            };
+        }
+        match level {
+            1 => event!(Level::ERROR),
+            2 => event!(Level::WARN),
+            3 => event!(Level::INFO),
+            4 => event!(Level::DEBUG),
+            _ => event!(Level::TRACE),
+        }
+    }
+}
+
+// Name of the guest span, as reported by the guest
+struct GuestSpanName(String);
+
+struct GuestSpanNameLayer;
+
+impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for GuestSpanNameLayer {
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if attrs.metadata().target() != GUEST_TARGET {
+            return;
+        }
+        let mut name = Name::default();
+        attrs.record(&mut name);
+        if let (Some(name), Some(span)) = (name.0, ctx.span(id)) {
+            span.extensions_mut().insert(GuestSpanName(name));
+        }
+    }
+}
+
+// Extracts the `name` field of guest spans, or the `message` of events
+#[derive(Default)]
+struct Name(Option<String>);
+
+impl Visit for Name {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "name" {
+            self.0 = Some(value.to_string());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        if field.name() == "message" {
+            self.0 = Some(format!("{value:?}"));
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use std::io;
+use std::os::fd::RawFd;
+use std::sync::{Mutex, Once, PoisonError};
+
+use signal_hook::consts::SIGWINCH;
+use signal_hook::iterator::Signals;
+
+use crate::events::{Event, EventQueue};
+
+/// The event pushed to the guest when the terminal is resized, with its new
+/// size encoded like [`encode_size`].
+pub const RESIZE_EVENT: &str = "__Resize";
+
+/// The size of a terminal, in characters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WindowSize {
+    pub columns: u16,
+    pub rows: u16,
+}
+
+/// Whether `fd` refers to a terminal.
+pub fn is_terminal(fd: RawFd) -> bool {
+    unsafe { libc::isatty(fd) == 1 }
+}
+
+/// Returns the size of the terminal of stdout, or of stdin if stdout isn't
+/// a terminal.
+pub fn size() -> Option<WindowSize> {
+    [libc::STDOUT_FILENO, libc::STDIN_FILENO]
+        .into_iter()
+        .find_map(|fd| {
+            let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+            let ret = unsafe { libc::ioctl(fd, libc::TIOCGWINSZ, &mut size) };
+            (ret == 0 && size.ws_col > 0).then_some(WindowSize {
+                columns: size.ws_col,
+                rows: size.ws_row,
+            })
+        })
+}
+
+/// Encodes a size as returned by the `TerminalSize` host function: the
+/// columns and the rows as little endian u16s, or nothing without a terminal.
+pub fn encode_size(size: Option<WindowSize>) -> Vec<u8> {
+    let Some(size) = size else {
+        return Vec::new();
+    };
+    let mut buffer = Vec::with_capacity(4);
+    buffer.extend_from_slice(&size.columns.to_le_bytes());
+    buffer.extend_from_slice(&size.rows.to_le_bytes());
+    buffer
+}
+
+// The mode of the terminal of stdin before it was set to raw mode
+static ORIGINAL_MODE: Mutex<Option<libc::termios>> = Mutex::new(None);
+
+/// Sets the terminal of stdin to raw mode, where input is available byte by
+/// byte, isn't echoed, and keys like Ctrl-C don't send signals. Output is
+/// still processed, so that `\n` starts a new line.
+///
+/// Disabling it restores the original mode, which is also restored when the
+/// process exits.
+pub fn set_raw_mode(enabled: bool) -> io::Result<()> {
+    let mut original = ORIGINAL_MODE.lock().unwrap_or_else(PoisonError::into_inner);
+    if !enabled {
+        if let Some(mode) = original.take() {
+            set_mode(&mode)?;
+        }
+        return Ok(());
+    }
+    if original.is_some() {
+        return Ok(());
+    }
+    let mut mode: libc::termios = unsafe { std::mem::zeroed() };
+    if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut mode) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    static RESTORE_AT_EXIT: Once = Once::new();
+    RESTORE_AT_EXIT.call_once(|| unsafe {
+        libc::atexit(restore_at_exit);
+    });
+    let mut raw = mode;
+    unsafe { libc::cfmakeraw(&mut raw) };
+    raw.c_oflag |= libc::OPOST;
+    set_mode(&raw)?;
+    *original = Some(mode);
+    Ok(())
+}
+
+fn set_mode(mode: &libc::termios) -> io::Result<()> {
+    if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, mode) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+extern "C" fn restore_at_exit() {
+    let _ = set_raw_mode(false);
+}
+
+/// Pushes a [`RESIZE_EVENT`] to `events` every time the terminal is resized.
+pub fn watch_resize(events: EventQueue) -> io::Result<()> {
+    let mut signals = Signals::new([SIGWINCH])?;
+    std::thread::spawn(move || {
+        for _ in signals.forever() {
+            events.push(Event::new(RESIZE_EVENT, encode_size(size())));
+        }
+    });
+    Ok(())
+}